@@ -0,0 +1,35 @@
+use std::io::Read;
+
+use smartaudio::dissect;
+use smartaudio::hex::HexBytes;
+use smartaudio::FieldValue;
+use smartaudio::ParserConfig;
+use smartaudio::SmartAudioParser;
+
+/// Decodes `SmartAudio` frames pasted as hex text, e.g.
+/// `echo "AA 55 09 06 01 00 1A 16 E9 0A" | cargo run --example hex_dump`.
+fn main() {
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .expect("stdin is not UTF-8 text");
+
+    let mut parser: SmartAudioParser = SmartAudioParser::with_config(ParserConfig::ANY);
+    for byte in HexBytes::new(&text) {
+        match parser.push_byte_raw(byte) {
+            Ok(Some(frame)) => {
+                println!("{:?} frame:", frame.origin());
+                for field in dissect(&frame) {
+                    let value = match field.value {
+                        FieldValue::Bytes(bytes) => format!("{bytes:02X?}"),
+                        value => format!("{value:?}"),
+                    };
+                    let mark = if field.valid { "" } else { "  <- invalid" };
+                    println!("  {:>2} {:<26} {value}{mark}", field.offset, field.name);
+                }
+            }
+            Ok(None) | Err(smartaudio::SmartAudioError::UnexpetedDataForState(..)) => (),
+            Err(error) => println!("error: {error:?}"),
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::parser::SmartAudioError;
+use crate::responses::Settings;
+use crate::responses::Version;
+
+/// Nominal `SmartAudio` baud rate.
+pub const NOMINAL_BAUD: u32 = 4800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BaudSearchConfig {
+    pub min_baud: u32,
+    pub max_baud: u32,
+    /// Distance between two candidate baud rates.
+    pub step: u32,
+    /// Failed `GetSettings` probes before moving to the next candidate.
+    pub attempts_per_baud: u8,
+    /// Transactions per tracking window once locked.
+    pub track_window: u8,
+    /// Failed transactions in a tracking window that trigger a baud adjustment.
+    pub track_max_failures: u8,
+}
+
+impl Default for BaudSearchConfig {
+    fn default() -> Self {
+        // +-5% around the nominal rate in 1% steps.
+        Self {
+            min_baud: NOMINAL_BAUD - 240,
+            max_baud: NOMINAL_BAUD + 240,
+            step: 48,
+            attempts_per_baud: 2,
+            track_window: 8,
+            track_max_failures: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DetectedVtx {
+    pub baud: u32,
+    pub version: Version,
+    pub settings: Settings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BaudUpdate {
+    /// The initial probe found a VTX, the baud rate is locked.
+    Detected(DetectedVtx),
+    /// The UART must be reconfigured to the given baud rate.
+    Changed(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Phase {
+    Searching { candidate: u32, attempts: u8 },
+    Locked,
+}
+
+/// Baud rate search driven by `GetSettings` outcomes.
+///
+/// While searching, candidates are tried outwards from [`NOMINAL_BAUD`]
+/// until a probe succeeds. Once locked, failures are counted per window and
+/// the baud rate is nudged by one step when a window had too many, reversing
+/// direction if the previous nudge made things worse.
+#[derive(Debug, Clone)]
+pub struct BaudSearch {
+    config: BaudSearchConfig,
+    phase: Phase,
+    baud: u32,
+    window_total: u8,
+    window_failures: u8,
+    previous_failures: Option<u8>,
+    step_up: bool,
+}
+
+impl BaudSearch {
+    pub fn new(config: BaudSearchConfig) -> Self {
+        Self {
+            config,
+            phase: Phase::Searching {
+                candidate: 0,
+                attempts: 0,
+            },
+            baud: NOMINAL_BAUD.clamp(config.min_baud, config.max_baud),
+            window_total: 0,
+            window_failures: 0,
+            previous_failures: None,
+            step_up: true,
+        }
+    }
+
+    /// Baud rate the UART should currently use.
+    pub fn baud(&self) -> u32 {
+        self.baud
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.phase == Phase::Locked
+    }
+
+    /// Restarts the search from the nominal baud rate.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// Reports a valid `GetSettings` reply, which ends the initial probe.
+    pub fn on_success(&mut self, settings: &Settings) -> Option<BaudUpdate> {
+        match self.phase {
+            Phase::Searching { .. } => {
+                self.phase = Phase::Locked;
+                Some(BaudUpdate::Detected(DetectedVtx {
+                    baud: self.baud,
+                    version: settings.version,
+                    settings: *settings,
+                }))
+            }
+            Phase::Locked => self.track(false),
+        }
+    }
+
+    /// Reports a failed transaction: a timeout when `error` is `None`, or a
+    /// CRC or header error.
+    ///
+    /// While searching, timeouts and CRC errors use up one of the
+    /// [`BaudSearchConfig::attempts_per_baud`] probes of the candidate, as
+    /// the VTX may just be slow to answer, or the rate close enough to get a
+    /// frame through. Header and length errors mean the bytes themselves are
+    /// garbled, so the candidate is dropped at once.
+    pub fn on_failure(&mut self, error: Option<&SmartAudioError>) -> Option<BaudUpdate> {
+        match self.phase {
+            Phase::Searching {
+                candidate,
+                attempts,
+            } => {
+                let garbled = !matches!(error, None | Some(SmartAudioError::InvalidCrc { .. }));
+                let attempts = attempts + 1;
+                if !garbled && attempts < self.config.attempts_per_baud {
+                    self.phase = Phase::Searching {
+                        candidate,
+                        attempts,
+                    };
+                    return None;
+                }
+                let (candidate, baud) = self.next_candidate(candidate);
+                self.phase = Phase::Searching {
+                    candidate,
+                    attempts: 0,
+                };
+                self.set_baud(baud)
+            }
+            Phase::Locked => self.track(true),
+        }
+    }
+
+    /// Next candidate in the `0, +1, -1, +2, -2, ...` step sequence that
+    /// fits the configured range, wrapping back to the nominal rate.
+    fn next_candidate(&self, candidate: u32) -> (u32, u32) {
+        let mut candidate = candidate;
+        loop {
+            candidate += 1;
+            let offset = candidate.div_ceil(2) * self.config.step;
+            let baud = if candidate % 2 == 1 {
+                NOMINAL_BAUD.checked_add(offset)
+            } else {
+                NOMINAL_BAUD.checked_sub(offset)
+            };
+            let in_range = |b: &u32| (self.config.min_baud..=self.config.max_baud).contains(b);
+            if let Some(baud) = baud.filter(in_range) {
+                return (candidate, baud);
+            }
+            // Past both ends of the range, start over.
+            let reach = NOMINAL_BAUD
+                .saturating_sub(self.config.min_baud)
+                .max(self.config.max_baud.saturating_sub(NOMINAL_BAUD));
+            if offset > reach || self.config.step == 0 {
+                return (
+                    0,
+                    NOMINAL_BAUD.clamp(self.config.min_baud, self.config.max_baud),
+                );
+            }
+        }
+    }
+
+    fn track(&mut self, failed: bool) -> Option<BaudUpdate> {
+        self.window_total += 1;
+        self.window_failures += u8::from(failed);
+        if self.window_total < self.config.track_window {
+            return None;
+        }
+        let failures = self.window_failures;
+        self.window_total = 0;
+        self.window_failures = 0;
+
+        if failures < self.config.track_max_failures {
+            self.previous_failures = None;
+            return None;
+        }
+        if self
+            .previous_failures
+            .is_some_and(|previous| failures > previous)
+        {
+            self.step_up = !self.step_up;
+        }
+        self.previous_failures = Some(failures);
+
+        let baud = if self.step_up {
+            self.baud.saturating_add(self.config.step)
+        } else {
+            self.baud.saturating_sub(self.config.step)
+        };
+        if !(self.config.min_baud..=self.config.max_baud).contains(&baud) {
+            self.step_up = !self.step_up;
+            return None;
+        }
+        self.set_baud(baud)
+    }
+
+    fn set_baud(&mut self, baud: u32) -> Option<BaudUpdate> {
+        if baud == self.baud {
+            return None;
+        }
+        self.baud = baud;
+        Some(BaudUpdate::Changed(baud))
+    }
+}
+
+impl Default for BaudSearch {
+    fn default() -> Self {
+        Self::new(BaudSearchConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::parser::State;
+    use std::vec::Vec;
+
+    fn settings() -> Settings {
+        Settings {
+            version: Version::V2_0,
+            frequency: 5865,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search_sequence() {
+        let mut search = BaudSearch::default();
+        assert_eq!(search.baud(), 4800);
+        let mut tried = Vec::new();
+        for _ in 0..12 {
+            assert_eq!(search.on_failure(None), None);
+            tried.push(search.on_failure(None));
+        }
+        let expected = [
+            4848, 4752, 4896, 4704, 4944, 4656, 4992, 4608, 5040, 4560, 4800, 4848,
+        ];
+        assert_eq!(tried, expected.map(|b| Some(BaudUpdate::Changed(b))));
+    }
+
+    #[test]
+    fn test_full_cycle_in_asymmetric_range() {
+        let mut search = BaudSearch::new(BaudSearchConfig {
+            min_baud: 4700,
+            max_baud: 5200,
+            step: 100,
+            attempts_per_baud: 1,
+            ..Default::default()
+        });
+        // One failed probe per candidate, back at nominal after the top end.
+        let mut tried = Vec::new();
+        while let Some(BaudUpdate::Changed(baud)) = search.on_failure(None) {
+            tried.push(baud);
+            if baud == NOMINAL_BAUD {
+                break;
+            }
+        }
+        assert_eq!(tried, [4900, 4700, 5000, 5100, 5200, 4800]);
+    }
+
+    #[test]
+    fn test_garbled_bytes_skip_candidate() {
+        let mut search = BaudSearch::default();
+        let crc = SmartAudioError::InvalidCrc {
+            calculated_crc: 0,
+            frame_crc: 1,
+        };
+        assert_eq!(search.on_failure(Some(&crc)), None);
+        assert_eq!(
+            search.on_failure(Some(&crc)),
+            Some(BaudUpdate::Changed(4848))
+        );
+        let garbage = SmartAudioError::UnexpetedDataForState(State::AwaitingHeader2, 0x00);
+        assert_eq!(
+            search.on_failure(Some(&garbage)),
+            Some(BaudUpdate::Changed(4752))
+        );
+        assert_eq!(
+            search.on_failure(Some(&SmartAudioError::InvalidHeader)),
+            Some(BaudUpdate::Changed(4896))
+        );
+    }
+
+    #[test]
+    fn test_lock_returns_detected_vtx() {
+        let mut search = BaudSearch::default();
+        search.on_failure(None);
+        search.on_failure(None);
+        let detected = search.on_success(&settings());
+        assert_eq!(
+            detected,
+            Some(BaudUpdate::Detected(DetectedVtx {
+                baud: 4848,
+                version: Version::V2_0,
+                settings: settings(),
+            }))
+        );
+        assert!(search.is_locked());
+        assert_eq!(search.on_success(&settings()), None);
+    }
+
+    #[test]
+    fn test_tracking_adjusts_baud() {
+        let mut search = BaudSearch::default();
+        search.on_success(&settings());
+
+        // A clean window keeps the baud rate.
+        for _ in 0..8 {
+            assert_eq!(search.on_success(&settings()), None);
+        }
+        assert_eq!(search.baud(), 4800);
+
+        // A bad window steps up.
+        let mut changed = None;
+        for i in 0..8 {
+            let update = if i % 2 == 0 {
+                search.on_failure(None)
+            } else {
+                search.on_success(&settings())
+            };
+            changed = changed.or(update);
+        }
+        assert_eq!(changed, Some(BaudUpdate::Changed(4848)));
+
+        // A worse window reverses direction.
+        let error = SmartAudioError::InvalidCrc {
+            calculated_crc: 0,
+            frame_crc: 1,
+        };
+        let mut changed = None;
+        for _ in 0..8 {
+            changed = changed.or(search.on_failure(Some(&error)));
+        }
+        assert_eq!(changed, Some(BaudUpdate::Changed(4800)));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::commands::SmartAudioCommand;
+use crate::constants::HEADER_BYTE_1;
+use crate::constants::HEADER_BYTE_2;
+use crate::crc::Crc8DvbS2;
+use crate::parser::frame_payload;
+use crate::parser::FrameOrigin;
+use crate::parser::SmartAudioError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum CrcMode {
+    Computed,
+    Fixed(u8),
+    /// Inverse of the CRC computed in [`RawFrameBuilder::build`].
+    Corrupt,
+}
+
+/// Builder for arbitrary, possibly malformed, frames.
+///
+/// Starts from a well formed frame and applies
+/// the requested faults in a fixed order: length override, CRC override,
+/// trailing bytes, dropped header byte and finally truncation. The CRC is
+/// computed after the length override, so a wrong length alone still has a
+/// valid CRC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RawFrameBuilder<'a> {
+    command: u8,
+    payload: &'a [u8],
+    origin: FrameOrigin,
+    length: Option<u8>,
+    crc: CrcMode,
+    trailing: &'a [u8],
+    drop_header_byte: Option<usize>,
+    truncate: Option<usize>,
+}
+
+impl<'a> RawFrameBuilder<'a> {
+    /// Host command frame, `command` is the byte on the wire.
+    pub const fn new(command: u8, payload: &'a [u8]) -> Self {
+        Self {
+            command,
+            payload,
+            origin: FrameOrigin::Host,
+            length: None,
+            crc: CrcMode::Computed,
+            trailing: &[],
+            drop_header_byte: None,
+            truncate: None,
+        }
+    }
+
+    /// VTX response frame, the length byte counts the CRC and the CRC skips the header.
+    pub const fn response(command: u8, payload: &'a [u8]) -> Self {
+        Self::new(command, payload).origin(FrameOrigin::Vtx)
+    }
+
+    pub const fn origin(mut self, origin: FrameOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Writes `length` instead of the real length byte.
+    pub const fn length(mut self, length: u8) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Writes `crc` instead of the computed CRC.
+    pub const fn crc(mut self, crc: u8) -> Self {
+        self.crc = CrcMode::Fixed(crc);
+        self
+    }
+
+    /// Writes the bitwise inverse of the computed CRC, which is always wrong
+    /// whatever other faults are applied.
+    pub const fn bad_crc(mut self) -> Self {
+        self.crc = CrcMode::Corrupt;
+        self
+    }
+
+    /// Appends `bytes` after the CRC.
+    pub const fn trailing(mut self, bytes: &'a [u8]) -> Self {
+        self.trailing = bytes;
+        self
+    }
+
+    /// Leaves out header byte `index`, 0 or 1. Panics on any other index.
+    pub const fn drop_header_byte(mut self, index: usize) -> Self {
+        assert!(index < 2, "a frame has two header bytes");
+        self.drop_header_byte = Some(index);
+        self
+    }
+
+    /// Cuts the frame after `len` bytes.
+    pub const fn truncate(mut self, len: usize) -> Self {
+        self.truncate = Some(len);
+        self
+    }
+
+    const fn length_byte(&self) -> u8 {
+        match (self.length, self.origin) {
+            (Some(length), _) => length,
+            (None, FrameOrigin::Host) => self.payload.len() as u8,
+            (None, FrameOrigin::Vtx) => self.payload.len() as u8 + 1,
+        }
+    }
+
+    const fn correct_crc(&self) -> u8 {
+        let mut crc = Crc8DvbS2::new();
+        if let FrameOrigin::Host = self.origin {
+            crc.update(&[HEADER_BYTE_1, HEADER_BYTE_2]);
+        }
+        crc.update(&[self.command, self.length_byte()]);
+        crc.update(self.payload);
+        crc.value()
+    }
+
+    /// Encodes the frame with its faults into `buffer`. Fails with
+    /// [`SmartAudioError::InvalidPayloadLength`] if the length byte cannot
+    /// count the payload.
+    pub fn build(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
+        let max_payload = match self.origin {
+            FrameOrigin::Host => usize::from(u8::MAX),
+            FrameOrigin::Vtx => usize::from(u8::MAX) - 1,
+        };
+        if self.payload.len() > max_payload {
+            return Err(SmartAudioError::InvalidPayloadLength);
+        }
+        let frame_len = self.payload.len() + 5;
+        let total = frame_len + self.trailing.len();
+        if buffer.len() < total {
+            return Err(SmartAudioError::BufferTooSmall(buffer.len()));
+        }
+        // A well formed host frame, the rest is applied on top of it.
+        frame_payload(buffer, self.command, self.payload)?;
+        if self.length.is_some() || matches!(self.origin, FrameOrigin::Vtx) {
+            buffer[3] = self.length_byte();
+            buffer[frame_len - 1] = self.correct_crc();
+        }
+        match self.crc {
+            CrcMode::Computed => (),
+            CrcMode::Fixed(crc) => buffer[frame_len - 1] = crc,
+            CrcMode::Corrupt => buffer[frame_len - 1] = !buffer[frame_len - 1],
+        }
+        buffer[frame_len..total].copy_from_slice(self.trailing);
+
+        let mut len = total;
+        if let Some(index) = self.drop_header_byte {
+            buffer.copy_within(index + 1..len, index);
+            len -= 1;
+        }
+        Ok(self.truncate.map_or(len, |truncate| truncate.min(len)))
+    }
+}
+
+impl SmartAudioCommand for RawFrameBuilder<'_> {
+    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
+        self.build(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::SetChannelCommand;
+    use crate::parser::SmartAudioParser;
+    use crate::parser::State;
+
+    fn build(builder: RawFrameBuilder) -> ([u8; 32], usize) {
+        let mut buffer = [0; 32];
+        let len = builder.build(&mut buffer).unwrap();
+        (buffer, len)
+    }
+
+    fn parse(bytes: &[u8]) -> Option<Result<(), SmartAudioError>> {
+        let mut parser = SmartAudioParser::new();
+        for byte in bytes {
+            match parser.push_byte_raw(*byte) {
+                Ok(Some(_)) => return Some(Ok(())),
+                Ok(None) => (),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_well_formed_frames() {
+        let (buffer, len) = build(RawFrameBuilder::new(0x07, &[0x00]));
+        assert_eq!(buffer[..len], SetChannelCommand { channel: 0 }.to_array());
+
+        let (buffer, len) = build(RawFrameBuilder::response(0x03, &[0x00, 0x01]));
+        assert_eq!(buffer[..len], [0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A]);
+        assert_eq!(parse(&buffer[..len]), Some(Ok(())));
+    }
+
+    #[test]
+    fn test_faults() {
+        let set_channel = RawFrameBuilder::response(0x03, &[0x00, 0x01]);
+
+        let (buffer, len) = build(set_channel.bad_crc());
+        assert_eq!(buffer[len - 1], !0x4A);
+        assert!(matches!(
+            parse(&buffer[..len]),
+            Some(Err(SmartAudioError::InvalidCrc { .. }))
+        ));
+
+        // The CRC stays wrong when faults are added after `bad_crc`.
+        let (buffer, len) = build(set_channel.bad_crc().length(0x02));
+        assert_eq!(buffer[3], 0x02);
+        assert_ne!(buffer[len - 1], set_channel.length(0x02).correct_crc());
+
+        let (buffer, len) = build(set_channel.length(0xFF));
+        assert_eq!(
+            parse(&buffer[..len]),
+            Some(Err(SmartAudioError::UnexpetedDataForState(
+                State::AwaitingLength,
+                0xFF
+            )))
+        );
+
+        let (buffer, len) = build(set_channel.drop_header_byte(1));
+        assert_eq!(buffer[..len], [0xAA, 0x03, 0x03, 0x00, 0x01, 0x4A]);
+        let (buffer, len) = build(set_channel.drop_header_byte(0));
+        assert_eq!(buffer[..len], [0x55, 0x03, 0x03, 0x00, 0x01, 0x4A]);
+
+        let (buffer, len) = build(set_channel.trailing(&[0x00, 0x00]).truncate(8));
+        assert_eq!(
+            buffer[..len],
+            [0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A, 0x00]
+        );
+
+        let (_, len) = build(set_channel.truncate(5));
+        assert_eq!(len, 5);
+        assert_eq!(
+            set_channel.build(&mut [0; 6]),
+            Err(SmartAudioError::BufferTooSmall(6))
+        );
+        assert_eq!(
+            RawFrameBuilder::response(0x03, &[0; 255]).build(&mut [0; 300]),
+            Err(SmartAudioError::InvalidPayloadLength)
+        );
+        assert_eq!(
+            RawFrameBuilder::new(0x03, &[0; 255]).build(&mut [0; 300]),
+            Ok(260)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "a frame has two header bytes")]
+    fn test_drop_header_byte_out_of_range() {
+        let _ = RawFrameBuilder::new(0x03, &[]).drop_header_byte(2);
+    }
+}
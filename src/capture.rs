@@ -0,0 +1,570 @@
+use std::fmt;
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+use std::string::String;
+use std::string::ToString;
+use std::time::Duration;
+use std::time::Instant;
+use std::vec::Vec;
+
+use crate::parser::SmartAudioError;
+use crate::parser::SmartAudioParser;
+use crate::responses::Response;
+use crate::sniffer::Exchange;
+use crate::sniffer::Sniffer;
+use crate::timing::wire_time_us;
+
+/// Magic at the start of binary captures.
+pub const BINARY_MAGIC: [u8; 4] = *b"SACP";
+/// First line of text captures, followed by the format version.
+pub const TEXT_MAGIC: &str = "# smartaudio capture v";
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Sender of a captured chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    HostToVtx,
+    VtxToHost,
+    /// Single wire tap that does not know who drove the line.
+    Unknown,
+}
+
+impl Direction {
+    fn tag(self) -> char {
+        match self {
+            Self::HostToVtx => 'H',
+            Self::VtxToHost => 'V',
+            Self::Unknown => '?',
+        }
+    }
+
+    fn from_tag(tag: char) -> Option<Self> {
+        match tag {
+            'H' => Some(Self::HostToVtx),
+            'V' => Some(Self::VtxToHost),
+            '?' => Some(Self::Unknown),
+            _ => None,
+        }
+    }
+}
+
+/// Bytes received back to back, stamped with the start bit of the first
+/// byte. [`Replay`] derives the end of each byte from it and the baud.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Microseconds since the start of the capture.
+    pub timestamp_us: u64,
+    pub direction: Direction,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    UnsupportedVersion(u8),
+    /// Malformed capture, carries the line for text captures, 0 for binary.
+    Format(usize),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "capture I/O error: {error}"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported capture version {version}")
+            }
+            Self::Format(0) => write!(f, "malformed binary capture"),
+            Self::Format(line) => write!(f, "malformed capture at line {line}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<io::Error> for CaptureError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Recorded session: line settings, free form notes and the chunks in order.
+///
+/// The binary format is [`BINARY_MAGIC`], the version byte, the baud as `u32`,
+/// the notes as `u16` length and UTF-8, then per chunk the timestamp as
+/// `u64`, the direction tag, the length as `u16` and the bytes, all little
+/// endian. The text format has the same content, one chunk per line:
+///
+/// ```text
+/// # smartaudio capture v1
+/// # baud 4800
+/// # note bench VTX, fresh battery
+/// 1000 H aa 55 03 00 9f
+/// 41250 V aa 55 09 06 01 00 1a 16 e9 0a
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Capture {
+    pub baud: u32,
+    pub notes: String,
+    pub chunks: Vec<Chunk>,
+}
+
+impl Capture {
+    pub fn new(baud: u32, notes: &str) -> Self {
+        Self {
+            baud,
+            notes: notes.to_string(),
+            chunks: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, timestamp_us: u64, direction: Direction, bytes: &[u8]) {
+        self.chunks.push(Chunk {
+            timestamp_us,
+            direction,
+            bytes: bytes.to_vec(),
+        });
+    }
+
+    /// Reads a binary or text capture, told apart by the first bytes.
+    pub fn read<R: Read>(reader: R) -> Result<Self, CaptureError> {
+        let mut reader = io::BufReader::new(reader);
+        if reader.fill_buf()?.starts_with(&BINARY_MAGIC) {
+            Self::read_binary(reader)
+        } else {
+            Self::read_text(reader)
+        }
+    }
+
+    fn read_binary<R: Read>(mut reader: R) -> Result<Self, CaptureError> {
+        let mut header = [0; 11];
+        reader.read_exact(&mut header)?;
+        if header[..4] != BINARY_MAGIC {
+            return Err(CaptureError::Format(0));
+        }
+        if header[4] != FORMAT_VERSION {
+            return Err(CaptureError::UnsupportedVersion(header[4]));
+        }
+        let baud = u32::from_le_bytes([header[5], header[6], header[7], header[8]]);
+        let mut notes = std::vec![0; usize::from(u16::from_le_bytes([header[9], header[10]]))];
+        reader.read_exact(&mut notes)?;
+        let notes = String::from_utf8(notes).map_err(|_| CaptureError::Format(0))?;
+
+        let mut capture = Self::new(baud, &notes);
+        let mut chunk = [0; 11];
+        loop {
+            // The capture may only end between chunks.
+            match read_up_to(&mut reader, &mut chunk)? {
+                0 => break,
+                11 => (),
+                _ => return Err(CaptureError::Format(0)),
+            }
+            let timestamp_us = u64::from_le_bytes(chunk[..8].try_into().expect("8 bytes"));
+            let direction =
+                Direction::from_tag(char::from(chunk[8])).ok_or(CaptureError::Format(0))?;
+            let mut bytes = std::vec![0; usize::from(u16::from_le_bytes([chunk[9], chunk[10]]))];
+            if read_up_to(&mut reader, &mut bytes)? != bytes.len() {
+                return Err(CaptureError::Format(0));
+            }
+            capture.chunks.push(Chunk {
+                timestamp_us,
+                direction,
+                bytes,
+            });
+        }
+        Ok(capture)
+    }
+
+    fn read_text<R: BufRead>(reader: R) -> Result<Self, CaptureError> {
+        let mut capture = Self::default();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let number = index + 1;
+            let line = line.trim();
+            if index == 0 {
+                let version = line
+                    .strip_prefix(TEXT_MAGIC)
+                    .and_then(|version| version.parse().ok())
+                    .ok_or(CaptureError::Format(number))?;
+                if version != FORMAT_VERSION {
+                    return Err(CaptureError::UnsupportedVersion(version));
+                }
+            } else if let Some(baud) = line.strip_prefix("# baud ") {
+                capture.baud = baud.parse().map_err(|_| CaptureError::Format(number))?;
+            } else if let Some(note) = line.strip_prefix("# note") {
+                if !capture.notes.is_empty() {
+                    capture.notes.push('\n');
+                }
+                capture
+                    .notes
+                    .push_str(note.strip_prefix(' ').unwrap_or(note));
+            } else if !line.is_empty() && !line.starts_with('#') {
+                let chunk = parse_chunk(line).ok_or(CaptureError::Format(number))?;
+                capture.chunks.push(chunk);
+            }
+        }
+        Ok(capture)
+    }
+
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let notes = self.notes.as_bytes();
+        let notes_len = u16::try_from(notes.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "notes too long"))?;
+        writer.write_all(&BINARY_MAGIC)?;
+        writer.write_all(&[FORMAT_VERSION])?;
+        writer.write_all(&self.baud.to_le_bytes())?;
+        writer.write_all(&notes_len.to_le_bytes())?;
+        writer.write_all(notes)?;
+        for chunk in &self.chunks {
+            write_binary_chunk(&mut writer, chunk)?;
+        }
+        Ok(())
+    }
+
+    pub fn write_text<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write_text_header(&mut writer, self.baud, &self.notes)?;
+        for chunk in &self.chunks {
+            write_text_chunk(&mut writer, chunk)?;
+        }
+        Ok(())
+    }
+
+    pub fn replay(&self) -> Replay<'_> {
+        Replay {
+            capture: self,
+            chunk: 0,
+            byte: 0,
+        }
+    }
+}
+
+fn parse_chunk(line: &str) -> Option<Chunk> {
+    let mut words = line.split_whitespace();
+    let timestamp_us = words.next()?.parse().ok()?;
+    let mut tag = words.next()?.chars();
+    let direction = Direction::from_tag(tag.next()?)?;
+    if tag.next().is_some() {
+        return None;
+    }
+    let bytes = words
+        .map(|word| u8::from_str_radix(word, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some(Chunk {
+        timestamp_us,
+        direction,
+        bytes,
+    })
+}
+
+/// Reads until `buffer` is full or `reader` ends, returns the bytes read.
+fn read_up_to<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => (),
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(filled)
+}
+
+fn write_binary_chunk<W: Write>(writer: &mut W, chunk: &Chunk) -> io::Result<()> {
+    let len = u16::try_from(chunk.bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "chunk too long"))?;
+    writer.write_all(&chunk.timestamp_us.to_le_bytes())?;
+    writer.write_all(&[chunk.direction.tag() as u8])?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(&chunk.bytes)
+}
+
+fn write_text_header<W: Write>(writer: &mut W, baud: u32, notes: &str) -> io::Result<()> {
+    writeln!(writer, "{TEXT_MAGIC}{FORMAT_VERSION}")?;
+    writeln!(writer, "# baud {baud}")?;
+    for note in notes.lines() {
+        writeln!(writer, "# note {note}")?;
+    }
+    Ok(())
+}
+
+fn write_text_chunk<W: Write>(writer: &mut W, chunk: &Chunk) -> io::Result<()> {
+    write!(writer, "{} {}", chunk.timestamp_us, chunk.direction.tag())?;
+    for byte in &chunk.bytes {
+        write!(writer, " {byte:02x}")?;
+    }
+    writeln!(writer)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Binary,
+    Text,
+}
+
+/// Streams chunks to a file while recording, without keeping them in memory.
+#[derive(Debug)]
+pub struct CaptureWriter<W: Write> {
+    writer: W,
+    format: Format,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    pub fn binary(writer: W, baud: u32, notes: &str) -> io::Result<Self> {
+        let mut writer = writer;
+        Capture::new(baud, notes).write_binary(&mut writer)?;
+        Ok(Self {
+            writer,
+            format: Format::Binary,
+        })
+    }
+
+    pub fn text(writer: W, baud: u32, notes: &str) -> io::Result<Self> {
+        let mut writer = writer;
+        write_text_header(&mut writer, baud, notes)?;
+        Ok(Self {
+            writer,
+            format: Format::Text,
+        })
+    }
+
+    pub fn record(
+        &mut self,
+        timestamp_us: u64,
+        direction: Direction,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        let chunk = Chunk {
+            timestamp_us,
+            direction,
+            bytes: bytes.to_vec(),
+        };
+        match self.format {
+            Format::Binary => write_binary_chunk(&mut self.writer, &chunk),
+            Format::Text => write_text_chunk(&mut self.writer, &chunk),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Captured byte with the time it finished arriving on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayedByte {
+    pub timestamp_us: u64,
+    pub direction: Direction,
+    pub byte: u8,
+}
+
+impl ReplayedByte {
+    /// Timestamp in the wrapping milliseconds used by the rest of the crate.
+    pub fn timestamp_ms(&self) -> u32 {
+        (self.timestamp_us / 1000) as u32
+    }
+
+    /// Time from the start of a [`Replay::paced`] replay at `speed` until
+    /// the byte is due.
+    pub fn due(&self, speed: f64) -> Duration {
+        Duration::from_secs_f64(self.timestamp_us as f64 / 1e6 / speed)
+    }
+}
+
+/// Bytes of a capture in order. Bytes of a chunk are spaced by their wire
+/// time at the capture baud from the chunk timestamp, so byte `i` is stamped
+/// with the end of its stop bit, `i + 1` byte times after the chunk start.
+#[derive(Debug, Clone)]
+pub struct Replay<'a> {
+    capture: &'a Capture,
+    chunk: usize,
+    byte: usize,
+}
+
+impl<'a> Replay<'a> {
+    /// Feeds the bytes not sent by the host through `parser`.
+    pub fn parse<const N: usize>(
+        self,
+        parser: &'a mut SmartAudioParser<N>,
+    ) -> impl Iterator<Item = (u64, Result<Response, SmartAudioError>)> + 'a {
+        self.filter(|byte| byte.direction != Direction::HostToVtx)
+            .filter_map(|byte| {
+                let result = parser.push_byte(byte.byte).transpose()?;
+                Some((byte.timestamp_us, result))
+            })
+    }
+
+    /// Feeds every byte through `sniffer` and flushes it at the end.
+    pub fn sniff(self, sniffer: &'a mut Sniffer) -> impl Iterator<Item = Exchange> + 'a {
+        let mut bytes = self;
+        let mut queued = None;
+        core::iter::from_fn(move || {
+            if let Some(exchange) = queued.take() {
+                return Some(exchange);
+            }
+            for byte in bytes.by_ref() {
+                let now = byte.timestamp_ms();
+                let expired = sniffer.poll(now);
+                let exchange = sniffer.push_byte(byte.byte, now);
+                match (expired, exchange) {
+                    (Some(expired), exchange) => {
+                        queued = exchange;
+                        return Some(expired);
+                    }
+                    (None, Some(exchange)) => return Some(exchange),
+                    (None, None) => (),
+                }
+            }
+            sniffer.flush()
+        })
+    }
+
+    /// Sleeps before each byte to reproduce the capture timing, `speed` 2.0
+    /// replays twice as fast.
+    pub fn paced(self, speed: f64) -> impl Iterator<Item = ReplayedByte> + 'a {
+        let start = Instant::now();
+        self.inspect(move |byte| {
+            if let Some(wait) = byte.due(speed).checked_sub(start.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        })
+    }
+}
+
+impl Iterator for Replay<'_> {
+    type Item = ReplayedByte;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let chunk = self.capture.chunks.get(self.chunk)?;
+            if let Some(&byte) = chunk.bytes.get(self.byte) {
+                self.byte += 1;
+                let offset = wire_time_us(self.capture.baud, self.byte);
+                return Some(ReplayedByte {
+                    timestamp_us: chunk.timestamp_us + u64::from(offset),
+                    direction: chunk.direction,
+                    byte,
+                });
+            }
+            self.chunk += 1;
+            self.byte = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GET_SETTINGS: [u8; 5] = [0xAA, 0x55, 0x03, 0x00, 0x9F];
+    const SETTINGS: [u8; 10] = [0xAA, 0x55, 0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9, 0x0A];
+
+    fn session() -> Capture {
+        let mut capture = Capture::new(4800, "bench VTX\nfresh battery");
+        capture.push(1_000, Direction::HostToVtx, &GET_SETTINGS);
+        capture.push(41_250, Direction::VtxToHost, &SETTINGS[..4]);
+        capture.push(50_420, Direction::VtxToHost, &SETTINGS[4..]);
+        capture
+    }
+
+    #[test]
+    fn test_binary_and_text_round_trip() {
+        let capture = session();
+        let mut binary = Vec::new();
+        capture.write_binary(&mut binary).unwrap();
+        assert_eq!(Capture::read(binary.as_slice()).unwrap(), capture);
+
+        let mut text = Vec::new();
+        capture.write_text(&mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.starts_with("# smartaudio capture v1\n# baud 4800\n# note bench VTX\n"));
+        assert!(text.contains("\n1000 H aa 55 03 00 9f\n"));
+        assert_eq!(Capture::read(text.as_bytes()).unwrap(), capture);
+
+        let mut writer =
+            CaptureWriter::binary(Vec::new(), 4800, "bench VTX\nfresh battery").unwrap();
+        for chunk in &capture.chunks {
+            writer
+                .record(chunk.timestamp_us, chunk.direction, &chunk.bytes)
+                .unwrap();
+        }
+        assert_eq!(writer.into_inner(), binary);
+    }
+
+    #[test]
+    fn test_malformed_captures() {
+        let text = "# smartaudio capture v1\n# baud 4800\n1000 X aa\n";
+        assert!(matches!(
+            Capture::read(text.as_bytes()),
+            Err(CaptureError::Format(3))
+        ));
+        let text = "# smartaudio capture v2\n";
+        assert!(matches!(
+            Capture::read(text.as_bytes()),
+            Err(CaptureError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            Capture::read(&b"SACP\x01\xc0\x12"[..]),
+            Err(CaptureError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_truncated_binary_capture() {
+        let capture = session();
+        let mut binary = Vec::new();
+        capture.write_binary(&mut binary).unwrap();
+        let first_chunk = 11 + capture.notes.len();
+        let second_chunk = first_chunk + 11 + GET_SETTINGS.len();
+
+        let read = Capture::read(&binary[..second_chunk]).unwrap();
+        assert_eq!(read.chunks, capture.chunks[..1]);
+        // Cuts inside a chunk header, then inside a chunk body.
+        let cuts = [
+            first_chunk + 1,
+            second_chunk + 5,
+            second_chunk + 10,
+            first_chunk + 11 + 2,
+            second_chunk + 11 + 1,
+        ];
+        for cut in cuts {
+            assert!(matches!(
+                Capture::read(&binary[..cut]),
+                Err(CaptureError::Format(0))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_replay() {
+        let capture = session();
+        let bytes: Vec<_> = capture.replay().collect();
+        assert_eq!(bytes.len(), 15);
+        assert_eq!(bytes[0].timestamp_us, 1_000 + 2_292);
+        assert_eq!(bytes[6].timestamp_us, 41_250 + 4_584);
+
+        let mut parser = SmartAudioParser::new();
+        let responses: Vec<_> = capture.replay().parse(&mut parser).collect();
+        assert!(matches!(
+            responses[..],
+            [(timestamp, Ok(Response::GetSettings(_)))] if timestamp == 50_420 + 13_750
+        ));
+
+        let mut sniffer = Sniffer::default();
+        let exchanges: Vec<_> = capture.replay().sniff(&mut sniffer).collect();
+        assert!(matches!(
+            exchanges[..],
+            [Exchange::Transaction {
+                sent_at: 12,
+                replied_at: 64,
+                ..
+            }]
+        ));
+
+        let last = capture.replay().last().unwrap();
+        assert_eq!(last.due(1.0), Duration::from_micros(50_420 + 13_750));
+        assert_eq!(last.due(10.0), Duration::from_micros(6_417));
+    }
+}
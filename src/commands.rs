@@ -1,19 +1,81 @@
+use core::ops::Deref;
+
 use crate::constants::command;
 use crate::constants::mode_flags;
-use crate::parser::frame_payload;
+use crate::constants::HEADER_BYTE_1;
+use crate::constants::HEADER_BYTE_2;
+use crate::crc::Crc8DvbS2;
+use crate::layout::smartaudio_frames;
+use crate::layout::FrameLayout;
+use crate::parser::FrameOrigin;
+use crate::parser::RawSmartAudioFrame;
 use crate::parser::SmartAudioError;
+use crate::responses::Response;
 
 pub trait SmartAudioCommand {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError>;
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
-pub struct GetSettingsCommand {}
+/// Encoded host command of exactly `N` bytes, header and CRC included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame<const N: usize> {
+    bytes: [u8; N],
+}
 
-impl SmartAudioCommand for GetSettingsCommand {
-    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
-        const PAYLOAD: [u8; 0] = [];
-        frame_payload(buffer, command::GET_SETTINGS, &PAYLOAD)
+impl<const N: usize> Frame<N> {
+    /// Encodes `command` with `payload`, `N` must be the payload length plus 5.
+    pub(crate) const fn encode(command: u8, payload: &[u8]) -> Self {
+        assert!(payload.len() + 5 == N, "frame size does not match payload");
+        let mut bytes = [0; N];
+        bytes[0] = HEADER_BYTE_1;
+        bytes[1] = HEADER_BYTE_2;
+        bytes[2] = command;
+        bytes[3] = payload.len() as u8;
+        let mut i = 0;
+        while i < payload.len() {
+            bytes[4 + i] = payload[i];
+            i += 1;
+        }
+        bytes[N - 1] = Crc8DvbS2::checksum(bytes.split_at(N - 1).0);
+        Self { bytes }
+    }
+
+    pub const fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub const fn into_array(self) -> [u8; N] {
+        self.bytes
+    }
+
+    /// Copies the frame to the start of `buffer`.
+    pub fn write_to(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
+        let Some(target) = buffer.get_mut(..N) else {
+            return Err(SmartAudioError::BufferTooSmall(buffer.len()));
+        };
+        target.copy_from_slice(&self.bytes);
+        Ok(N)
+    }
+}
+
+impl<const N: usize> Deref for Frame<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<const N: usize> AsRef<[u8]> for Frame<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl<const N: usize> From<Frame<N>> for [u8; N] {
+    fn from(frame: Frame<N>) -> Self {
+        frame.bytes
     }
 }
 
@@ -25,6 +87,24 @@ pub enum Power {
     dBm(u8),
 }
 
+impl Power {
+    /// Payload byte, dBm values have the MSB set.
+    pub const fn to_byte(self) -> u8 {
+        match self {
+            Power::dBm(value) => value | 0b1000_0000,
+            Power::Level(value) => value,
+        }
+    }
+
+    pub const fn from_byte(byte: u8) -> Self {
+        if byte & 0b1000_0000 != 0 {
+            Power::dBm(byte & 0b0111_1111)
+        } else {
+            Power::Level(byte)
+        }
+    }
+}
+
 impl Default for Power {
     fn default() -> Self {
         Self::Level(0)
@@ -33,61 +113,99 @@ impl Default for Power {
 
 impl From<Power> for u8 {
     fn from(power: Power) -> Self {
-        match power {
-            Power::dBm(value) => value | 0b1000_0000,
-            Power::Level(value) => value,
-        }
+        power.to_byte()
     }
 }
 
-pub struct SetPowerCommand {
-    pub power: Power,
-}
+smartaudio_frames! {
+    #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Host struct GetSettingsCommand(command::GET_SETTINGS) {}
 
-impl SmartAudioCommand for SetPowerCommand {
-    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
-        let payload = [self.power.into()];
-        frame_payload(buffer, command::SET_POWER, &payload)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Host struct SetPowerCommand(command::SET_POWER) {
+        pub power: Power,
     }
-}
 
-pub struct SetChannelCommand {
-    pub channel: u8,
-}
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Host struct SetChannelCommand(command::SET_CHANNEL) {
+        pub channel: u8,
+    }
 
-impl SmartAudioCommand for SetChannelCommand {
-    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
-        let payload = [self.channel];
-        frame_payload(buffer, command::SET_CHANNEL, &payload)
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Host struct SetFrequencyCommand(command::SET_FREQUENCY) {
+        pub frequency: u16,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Host struct SetModeCommand(command::SET_MODE) {
+        flags {
+            pub pitmode_in_range_active = mode_flags::PITMODE_IN_RANGE,
+            pub pitmode_out_range_active = mode_flags::PITMODE_OUT_RANGE,
+            pub pitmode_enabled = mode_flags::PITMODE_ENABLED,
+            pub unlocked = mode_flags::UNLOCKED,
+        },
     }
 }
 
-pub struct SetFrequencyCommand {
-    pub frequency: u16,
+/// Host command decoded from the wire, e.g. by [`crate::sniffer::Sniffer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Command {
+    GetSettings(GetSettingsCommand),
+    SetPower(SetPowerCommand),
+    SetChannel(SetChannelCommand),
+    SetFrequency(SetFrequencyCommand),
+    SetMode(SetModeCommand),
 }
 
-impl SmartAudioCommand for SetFrequencyCommand {
-    fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
-        let payload = self.frequency.to_be_bytes();
-        frame_payload(buffer, command::SET_FREQUENCY, &payload)
+impl Command {
+    pub fn parse(raw_frame: &RawSmartAudioFrame<'_>) -> Result<Self, SmartAudioError> {
+        let cmd = raw_frame.commnand();
+        if raw_frame.origin() == FrameOrigin::Vtx {
+            return Err(SmartAudioError::UnknownCommand(cmd));
+        }
+        let payload = raw_frame.payload();
+        match cmd {
+            command::GET_SETTINGS => {
+                GetSettingsCommand::decode(cmd, payload).map(Self::GetSettings)
+            }
+            command::SET_POWER => SetPowerCommand::decode(cmd, payload).map(Self::SetPower),
+            command::SET_CHANNEL => SetChannelCommand::decode(cmd, payload).map(Self::SetChannel),
+            command::SET_FREQUENCY => {
+                SetFrequencyCommand::decode(cmd, payload).map(Self::SetFrequency)
+            }
+            command::SET_MODE => SetModeCommand::decode(cmd, payload).map(Self::SetMode),
+            _ => Err(SmartAudioError::UnknownCommand(cmd)),
+        }
     }
-}
 
-pub struct SetModeCommand {
-    pub pitmode_in_range_active: bool,
-    pub pitmode_out_range_active: bool,
-    pub pitmode_enabled: bool,
-    pub unlocked: bool,
+    /// Whether `response` is the kind of reply the VTX sends to this command.
+    pub fn is_answered_by(&self, response: &Response) -> bool {
+        matches!(
+            (self, response),
+            (Self::GetSettings(_), Response::GetSettings(_))
+                | (Self::SetPower(_), Response::SetPower(_))
+                | (Self::SetChannel(_), Response::SetChannel(_))
+                | (Self::SetFrequency(_), Response::SetFrequency(_))
+                | (Self::SetMode(_), Response::SetMode(_))
+        )
+    }
 }
 
-impl SmartAudioCommand for SetModeCommand {
+impl SmartAudioCommand for Command {
     fn to_bytes(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError> {
-        let mode = (u8::from(self.pitmode_in_range_active) * mode_flags::PITMODE_IN_RANGE)
-            | (u8::from(self.pitmode_out_range_active) * mode_flags::PITMODE_OUT_RANGE)
-            | (u8::from(self.pitmode_enabled) * mode_flags::PITMODE_ENABLED)
-            | (u8::from(self.unlocked) * mode_flags::UNLOCKED);
-        let payload = [mode];
-        frame_payload(buffer, command::SET_MODE, &payload)
+        match self {
+            Self::GetSettings(command) => command.to_bytes(buffer),
+            Self::SetPower(command) => command.to_bytes(buffer),
+            Self::SetChannel(command) => command.to_bytes(buffer),
+            Self::SetFrequency(command) => command.to_bytes(buffer),
+            Self::SetMode(command) => command.to_bytes(buffer),
+        }
     }
 }
 
@@ -157,4 +275,27 @@ mod tesst {
         let expected: [u8; 6] = [0xAA, 0x55, 0x0B, 0x01, 0x0A, 0x7B];
         assert_eq!(expected, buffer[0..size]);
     }
+
+    #[test]
+    fn test_const_frames() {
+        static GET_SETTINGS: [u8; 5] = GetSettingsCommand {}.to_array();
+        const PIT_MODE_OFF: Frame<6> = SetModeCommand {
+            pitmode_in_range_active: false,
+            pitmode_out_range_active: false,
+            pitmode_enabled: false,
+            unlocked: true,
+        }
+        .to_frame();
+        assert_eq!(GET_SETTINGS, [0xAA, 0x55, 0x03, 0x00, 0x9F]);
+        assert_eq!(PIT_MODE_OFF.len(), 6);
+        assert_eq!(PIT_MODE_OFF[4], mode_flags::UNLOCKED);
+
+        let frame = SetFrequencyCommand { frequency: 5865 }.to_frame();
+        assert_eq!(frame.as_bytes(), [0xAA, 0x55, 0x09, 0x02, 0x16, 0xE9, 0xDC]);
+        let mut buffer = [0; 6];
+        assert_eq!(
+            frame.write_to(&mut buffer),
+            Err(SmartAudioError::BufferTooSmall(6))
+        );
+    }
 }
@@ -0,0 +1,137 @@
+/// CRC8/DVB-S2 polynomial used by `SmartAudio`.
+const POLYNOMIAL: u8 = 0xD5;
+
+const fn update_bitwise(crc: u8, byte: u8) -> u8 {
+    let mut crc = crc ^ byte;
+    let mut i = 0;
+    while i < 8 {
+        crc = if crc & 0x80 != 0 {
+            (crc << 1) ^ POLYNOMIAL
+        } else {
+            crc << 1
+        };
+        i += 1;
+    }
+    crc
+}
+
+#[cfg(feature = "crc-table")]
+const TABLE: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = update_bitwise(0, i as u8);
+        i += 1;
+    }
+    table
+};
+
+#[cfg(feature = "crc-table")]
+const fn update_byte(crc: u8, byte: u8) -> u8 {
+    TABLE[(crc ^ byte) as usize]
+}
+
+#[cfg(not(feature = "crc-table"))]
+const fn update_byte(crc: u8, byte: u8) -> u8 {
+    update_bitwise(crc, byte)
+}
+
+/// Incremental CRC8/DVB-S2.
+///
+/// Uses a 256 byte lookup table with the `crc-table` feature, and the
+/// smaller bit by bit implementation otherwise.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Crc8DvbS2 {
+    crc: u8,
+}
+
+impl Crc8DvbS2 {
+    pub const fn new() -> Self {
+        Self { crc: 0 }
+    }
+
+    /// Resumes a CRC from a previously computed value.
+    pub const fn from_value(crc: u8) -> Self {
+        Self { crc }
+    }
+
+    pub const fn update_byte(&mut self, byte: u8) {
+        self.crc = update_byte(self.crc, byte);
+    }
+
+    pub const fn update(&mut self, data: &[u8]) {
+        let mut i = 0;
+        while i < data.len() {
+            self.update_byte(data[i]);
+            i += 1;
+        }
+    }
+
+    pub const fn value(&self) -> u8 {
+        self.crc
+    }
+
+    /// CRC of `data` in one go.
+    pub const fn checksum(data: &[u8]) -> u8 {
+        let mut crc = Self::new();
+        crc.update(data);
+        crc.value()
+    }
+}
+
+impl core::hash::Hasher for Crc8DvbS2 {
+    fn finish(&self) -> u64 {
+        u64::from(self.crc)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.update(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::hash::Hasher;
+
+    #[test]
+    fn test_known_values() {
+        assert_eq!(Crc8DvbS2::checksum(&[0xAA, 0x55, 0x03, 0x00]), 0x9F);
+        assert_eq!(
+            Crc8DvbS2::checksum(&[0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9]),
+            0x0A
+        );
+        assert_eq!(Crc8DvbS2::checksum(&[]), 0x00);
+    }
+
+    #[test]
+    fn test_incremental_matches_one_shot() {
+        let data = [0xAA, 0x55, 0x0B, 0x01, 0x0A];
+        let mut crc = Crc8DvbS2::new();
+        for byte in data {
+            crc.update_byte(byte);
+        }
+        assert_eq!(crc.value(), Crc8DvbS2::checksum(&data));
+        assert_eq!(crc.value(), 0x7B);
+
+        let mut resumed = Crc8DvbS2::from_value(Crc8DvbS2::checksum(&data[..2]));
+        resumed.write(&data[2..]);
+        assert_eq!(resumed.finish(), 0x7B);
+    }
+
+    #[test]
+    fn test_update_matches_bitwise() {
+        for crc in 0..=255u8 {
+            for byte in [0x00, 0x01, 0x55, 0xAA, 0xFF] {
+                assert_eq!(update_byte(crc, byte), update_bitwise(crc, byte));
+            }
+        }
+    }
+
+    #[test]
+    fn test_const_evaluation() {
+        const CRC: u8 = Crc8DvbS2::checksum(&[0xAA, 0x55, 0x07, 0x01, 0x00]);
+        assert_eq!(CRC, 0xB8);
+    }
+}
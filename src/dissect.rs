@@ -0,0 +1,337 @@
+use crate::commands::GetSettingsCommand;
+use crate::commands::Power;
+use crate::commands::SetChannelCommand;
+use crate::commands::SetFrequencyCommand;
+use crate::commands::SetModeCommand;
+use crate::commands::SetPowerCommand;
+use crate::constants::command;
+use crate::constants::response as resp;
+use crate::constants::HEADER_BYTE_1;
+use crate::constants::HEADER_BYTE_2;
+use crate::crc::Crc8DvbS2;
+use crate::layout::FieldInfo;
+use crate::layout::FieldKind;
+use crate::layout::FrameLayout;
+use crate::parser::FrameOrigin;
+use crate::parser::RawSmartAudioFrame;
+use crate::responses::SetChannelResponse;
+use crate::responses::SetFrequencyResponse;
+use crate::responses::SetModeResponse;
+use crate::responses::SetPowerResponse;
+use crate::responses::Settings;
+use crate::responses::Version;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FieldValue<'a> {
+    Byte(u8),
+    U16(u16),
+    Flag(bool),
+    Power(Power),
+    Version(Version),
+    /// Bytes without a known meaning, or a field cut off by a short frame.
+    Bytes(&'a [u8]),
+}
+
+/// One field of a dissected frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Field<'a> {
+    pub name: &'static str,
+    /// Offset from the first header byte.
+    pub offset: usize,
+    /// Length the field should have, `raw` is shorter when it is cut off.
+    pub len: usize,
+    pub raw: &'a [u8],
+    pub value: FieldValue<'a>,
+    /// Header, command, length and CRC match the protocol and the field is
+    /// complete. Bits of a flags byte share the byte and its offset.
+    pub valid: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Header,
+    Command,
+    Length,
+    Payload(usize),
+    Extra,
+    Crc,
+    Done,
+}
+
+/// Field table of the frames this crate knows, by origin and command byte.
+fn layout(origin: FrameOrigin, command: u8) -> Option<&'static [FieldInfo]> {
+    let fields = match (origin, command) {
+        (FrameOrigin::Host, command::GET_SETTINGS) => GetSettingsCommand::FIELDS,
+        (FrameOrigin::Host, command::SET_POWER) => SetPowerCommand::FIELDS,
+        (FrameOrigin::Host, command::SET_CHANNEL) => SetChannelCommand::FIELDS,
+        (FrameOrigin::Host, command::SET_FREQUENCY) => SetFrequencyCommand::FIELDS,
+        (FrameOrigin::Host, command::SET_MODE) => SetModeCommand::FIELDS,
+        (
+            FrameOrigin::Vtx,
+            resp::GET_SETTINGS_V1_0 | resp::GET_SETTINGS_V2_0 | resp::GET_SETTINGS_V2_1,
+        ) => Settings::FIELDS,
+        (FrameOrigin::Vtx, resp::SET_POWER) => SetPowerResponse::FIELDS,
+        (FrameOrigin::Vtx, resp::SET_CHANNEL) => SetChannelResponse::FIELDS,
+        (FrameOrigin::Vtx, resp::SET_FREQUENCY) => SetFrequencyResponse::FIELDS,
+        (FrameOrigin::Vtx, resp::SET_MODE) => SetModeResponse::FIELDS,
+        _ => return None,
+    };
+    Some(fields)
+}
+
+/// Splits `frame` into its fields, from the header to the CRC.
+///
+/// Payload fields come from the frame tables in [`crate::layout`], including
+/// the reserved byte of most VTX responses. Bytes after the known fields are
+/// reported as one `extra` field, and the whole payload of an unknown command
+/// as `payload`.
+pub fn dissect<'a>(frame: &RawSmartAudioFrame<'a>) -> Dissect<'a> {
+    let bytes = frame.as_bytes();
+    Dissect {
+        bytes,
+        origin: frame.origin(),
+        layout: layout(frame.origin(), frame.commnand()),
+        payload_end: bytes.len().saturating_sub(1).max(4),
+        covered: 4,
+        step: Step::Header,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Dissect<'a> {
+    bytes: &'a [u8],
+    origin: FrameOrigin,
+    layout: Option<&'static [FieldInfo]>,
+    payload_end: usize,
+    /// End of the payload fields reported so far.
+    covered: usize,
+    step: Step,
+}
+
+impl<'a> Dissect<'a> {
+    fn field(&self, name: &'static str, offset: usize, len: usize) -> Field<'a> {
+        let end = (offset + len).min(self.bytes.len());
+        let raw = &self.bytes[offset.min(end)..end];
+        Field {
+            name,
+            offset,
+            len,
+            raw,
+            value: FieldValue::Bytes(raw),
+            valid: raw.len() == len,
+        }
+    }
+
+    fn header(&self) -> Field<'a> {
+        let field = self.field("header", 0, 2);
+        Field {
+            valid: field.raw == [HEADER_BYTE_1, HEADER_BYTE_2],
+            ..field
+        }
+    }
+
+    fn command(&self) -> Field<'a> {
+        let command = self.bytes[2];
+        let version = self
+            .layout
+            .and_then(|fields| fields.iter().find(|field| field.kind == FieldKind::Version));
+        let (name, value) = match version {
+            Some(info) => (info.name, FieldValue::Version(Version::from(command))),
+            None => ("command", FieldValue::Byte(command)),
+        };
+        Field {
+            name,
+            value,
+            valid: self.layout.is_some(),
+            ..self.field(name, 2, 1)
+        }
+    }
+
+    fn length(&self) -> Field<'a> {
+        let length = self.bytes[3];
+        let expected = match self.origin {
+            FrameOrigin::Host => self.bytes.len().checked_sub(5),
+            FrameOrigin::Vtx => self.bytes.len().checked_sub(4),
+        };
+        Field {
+            value: FieldValue::Byte(length),
+            valid: expected == Some(usize::from(length)),
+            ..self.field("length", 3, 1)
+        }
+    }
+
+    fn payload_field(&self, info: &FieldInfo) -> Field<'a> {
+        let mut field = self.field(info.name, info.offset, info.len);
+        field.valid &= info.offset + info.len <= self.payload_end;
+        if !field.valid {
+            return field;
+        }
+        let raw = field.raw;
+        field.value = match info.kind {
+            FieldKind::Version => FieldValue::Version(Version::from(raw[0])),
+            FieldKind::U8 => FieldValue::Byte(raw[0]),
+            FieldKind::U16 => FieldValue::U16(u16::from_be_bytes([raw[0], raw[1]])),
+            FieldKind::Power => FieldValue::Power(Power::from_byte(raw[0])),
+            FieldKind::Flag(mask) => FieldValue::Flag(raw[0] & mask != 0),
+            FieldKind::Reserved => FieldValue::Bytes(raw),
+        };
+        field
+    }
+
+    fn crc(&self) -> Field<'a> {
+        let end = self.bytes.len() - 1;
+        let calculated = match self.origin {
+            FrameOrigin::Host => Crc8DvbS2::checksum(&self.bytes[..end]),
+            FrameOrigin::Vtx => Crc8DvbS2::checksum(&self.bytes[2..end]),
+        };
+        Field {
+            value: FieldValue::Byte(self.bytes[end]),
+            valid: self.bytes[end] == calculated,
+            ..self.field("crc", end, 1)
+        }
+    }
+}
+
+impl<'a> Iterator for Dissect<'a> {
+    type Item = Field<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (field, next) = match self.step {
+                Step::Header => (Some(self.header()), Step::Command),
+                Step::Command => (Some(self.command()), Step::Length),
+                Step::Length => (Some(self.length()), Step::Payload(0)),
+                Step::Payload(index) => {
+                    let Some(info) = self.layout.and_then(|fields| fields.get(index)) else {
+                        self.step = Step::Extra;
+                        continue;
+                    };
+                    let skip = info.kind == FieldKind::Version
+                        || (info.optional && info.offset >= self.payload_end);
+                    let field = (!skip).then(|| self.payload_field(info));
+                    if field.is_some() {
+                        self.covered = self.covered.max(info.offset + info.len);
+                    }
+                    (field, Step::Payload(index + 1))
+                }
+                Step::Extra => {
+                    let len = self.payload_end.saturating_sub(self.covered);
+                    let name = match self.layout {
+                        Some(_) => "extra",
+                        None => "payload",
+                    };
+                    (
+                        (len > 0).then(|| self.field(name, self.covered, len)),
+                        Step::Crc,
+                    )
+                }
+                Step::Crc => ((self.bytes.len() > 4).then(|| self.crc()), Step::Done),
+                Step::Done => return None,
+            };
+            self.step = next;
+            if field.is_some() {
+                return field;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use std::vec::Vec;
+
+    fn dissect_bytes(bytes: &[u8], origin: FrameOrigin) -> Vec<Field<'_>> {
+        dissect(&RawSmartAudioFrame::with_origin(bytes, origin).unwrap()).collect()
+    }
+
+    #[test]
+    fn test_dissect_set_mode_command() {
+        let raw = [0xAA, 0x55, 0x0B, 0x01, 0x0A, 0x7B];
+        let fields = dissect_bytes(&raw, FrameOrigin::Host);
+        let names: Vec<_> = fields.iter().map(|field| field.name).collect();
+        assert_eq!(
+            names,
+            [
+                "header",
+                "command",
+                "length",
+                "pitmode_in_range_active",
+                "pitmode_out_range_active",
+                "pitmode_enabled",
+                "unlocked",
+                "crc"
+            ]
+        );
+        assert!(fields.iter().all(|field| field.valid));
+        assert_eq!(fields[4].value, FieldValue::Flag(true));
+        assert_eq!(fields[5].value, FieldValue::Flag(false));
+        assert_eq!((fields[6].offset, fields[6].raw), (4, &raw[4..5]));
+    }
+
+    #[test]
+    fn test_dissect_settings_v21() {
+        let raw = [
+            0xAA, 0x55, 0x11, 0x0C, 0x00, 0x00, 0x00, 0x16, 0xE9, 0x0E, 0x03, 0x00, 0x0E, 0x14,
+            0x1A, 0x01,
+        ];
+        let fields = dissect_bytes(&raw, FrameOrigin::Vtx);
+        assert_eq!(fields[1].name, "version");
+        assert_eq!(fields[1].value, FieldValue::Version(Version::V2_1));
+        let frequency = fields.iter().find(|f| f.name == "frequency").unwrap();
+        assert_eq!(
+            (frequency.offset, frequency.value),
+            (7, FieldValue::U16(5865))
+        );
+        let last = &fields[fields.len() - 2];
+        assert_eq!(
+            (last.name, last.value),
+            ("dbm_level_4", FieldValue::Byte(0x1A))
+        );
+        assert!(fields.iter().all(|field| field.valid));
+    }
+
+    #[test]
+    fn test_dissect_reserved_and_bad_crc() {
+        let raw = [0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x00];
+        let fields = dissect_bytes(&raw, FrameOrigin::Vtx);
+        let summary: Vec<_> = fields.iter().map(|f| (f.name, f.offset, f.valid)).collect();
+        assert_eq!(
+            summary,
+            [
+                ("header", 0, true),
+                ("command", 2, true),
+                ("length", 3, true),
+                ("channel", 4, true),
+                ("reserved", 5, true),
+                ("crc", 6, false),
+            ]
+        );
+
+        // The reserved byte is optional, anything after it is extra.
+        let names = |bytes| {
+            dissect_bytes(bytes, FrameOrigin::Vtx)
+                .iter()
+                .map(|f| f.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&[0xAA, 0x55, 0x03, 0x02, 0x00, 0x00]),
+            ["header", "command", "length", "channel", "crc"]
+        );
+        assert_eq!(
+            names(&[0xAA, 0x55, 0x03, 0x04, 0x00, 0x01, 0x02, 0x00]),
+            ["header", "command", "length", "channel", "reserved", "extra", "crc"]
+        );
+
+        let unknown = [0xAA, 0x55, 0x7F, 0x01, 0x42, 0x00];
+        let fields = dissect_bytes(&unknown, FrameOrigin::Vtx);
+        assert!(!fields[1].valid);
+        assert!(!fields[2].valid);
+        assert_eq!(fields[3].name, "payload");
+        assert_eq!(fields[3].value, FieldValue::Bytes(&[0x42]));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::commands::Power;
+use crate::responses::Settings;
+use crate::responses::Version;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PitMode {
+    pub enabled: bool,
+    pub in_range_active: bool,
+    pub out_range_active: bool,
+}
+
+impl From<&Settings> for PitMode {
+    fn from(settings: &Settings) -> Self {
+        Self {
+            enabled: settings.pitmode_enabled,
+            in_range_active: settings.pitmode_in_range_active,
+            out_range_active: settings.pitmode_out_range_active,
+        }
+    }
+}
+
+/// Power reported by the settings: dBm for `SmartAudio` 2.1, level index otherwise.
+fn reported_power(settings: &Settings) -> Power {
+    match settings.power_settings {
+        Some(power_settings) => Power::dBm(power_settings.current_power),
+        None => Power::Level(settings.power_level),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VtxEvent {
+    ChannelChanged { from: u8, to: u8 },
+    FrequencyChanged { from: u16, to: u16 },
+    PowerChanged { from: Power, to: Power },
+    PitModeChanged { from: PitMode, to: PitMode },
+    UnlockStateChanged { unlocked: bool },
+    VersionChanged { from: Version, to: Version },
+}
+
+const MAX_EVENTS: usize = 6;
+
+/// Events produced by a single [`ChangeDetector::update`].
+#[derive(Debug, Clone)]
+pub struct Changes {
+    events: [Option<VtxEvent>; MAX_EVENTS],
+    position: usize,
+}
+
+impl Changes {
+    fn new() -> Self {
+        Self {
+            events: [None; MAX_EVENTS],
+            position: 0,
+        }
+    }
+
+    fn push(&mut self, event: VtxEvent) {
+        if let Some(slot) = self.events.iter_mut().find(|e| e.is_none()) {
+            *slot = Some(event);
+        }
+    }
+}
+
+impl Iterator for Changes {
+    type Item = VtxEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.events.get(self.position).copied().flatten();
+        self.position += 1;
+        event
+    }
+}
+
+/// Diffs successive `GetSettings` replies into [`VtxEvent`]s, so changes made
+/// out of band (e.g. with the button on the VTX) are noticed by the host.
+#[derive(Debug, Default, Clone)]
+pub struct ChangeDetector {
+    previous: Option<Settings>,
+}
+
+impl ChangeDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+
+    /// Settings the next update will be compared against.
+    pub fn baseline(&self) -> Option<&Settings> {
+        self.previous.as_ref()
+    }
+
+    /// Compares `settings` with the previous ones. The first update only sets
+    /// the baseline and yields no events.
+    pub fn update(&mut self, settings: &Settings) -> Changes {
+        let mut changes = Changes::new();
+        let Some(previous) = self.previous.replace(*settings) else {
+            return changes;
+        };
+
+        if previous.version != settings.version {
+            changes.push(VtxEvent::VersionChanged {
+                from: previous.version,
+                to: settings.version,
+            });
+        }
+        if previous.channel != settings.channel {
+            changes.push(VtxEvent::ChannelChanged {
+                from: previous.channel,
+                to: settings.channel,
+            });
+        }
+        if previous.frequency != settings.frequency {
+            changes.push(VtxEvent::FrequencyChanged {
+                from: previous.frequency,
+                to: settings.frequency,
+            });
+        }
+        let (from, to) = (reported_power(&previous), reported_power(settings));
+        if from != to {
+            changes.push(VtxEvent::PowerChanged { from, to });
+        }
+        let (from, to) = (PitMode::from(&previous), PitMode::from(settings));
+        if from != to {
+            changes.push(VtxEvent::PitModeChanged { from, to });
+        }
+        if previous.unlocked != settings.unlocked {
+            changes.push(VtxEvent::UnlockStateChanged {
+                unlocked: settings.unlocked,
+            });
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::responses::PowerSettings;
+    use std::vec::Vec;
+
+    fn settings() -> Settings {
+        Settings {
+            version: Version::V2_0,
+            channel: 1,
+            frequency: 5865,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_first_update_sets_baseline() {
+        let mut detector = ChangeDetector::new();
+        assert_eq!(detector.update(&settings()).count(), 0);
+        assert_eq!(detector.baseline(), Some(&settings()));
+        assert_eq!(detector.update(&settings()).count(), 0);
+    }
+
+    #[test]
+    fn test_channel_and_pit_mode_changes() {
+        let mut detector = ChangeDetector::new();
+        detector.update(&settings());
+        let changed = Settings {
+            channel: 32,
+            frequency: 5658,
+            pitmode_enabled: true,
+            unlocked: true,
+            ..settings()
+        };
+        let events: Vec<_> = detector.update(&changed).collect();
+        assert_eq!(
+            events,
+            [
+                VtxEvent::ChannelChanged { from: 1, to: 32 },
+                VtxEvent::FrequencyChanged {
+                    from: 5865,
+                    to: 5658
+                },
+                VtxEvent::PitModeChanged {
+                    from: PitMode::default(),
+                    to: PitMode {
+                        enabled: true,
+                        ..Default::default()
+                    }
+                },
+                VtxEvent::UnlockStateChanged { unlocked: true },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_power_and_version_changes() {
+        let mut detector = ChangeDetector::new();
+        detector.update(&settings());
+        let changed = Settings {
+            version: Version::V2_1,
+            power_settings: Some(PowerSettings {
+                current_power: 14,
+                ..Default::default()
+            }),
+            ..settings()
+        };
+        let events: Vec<_> = detector.update(&changed).collect();
+        assert_eq!(
+            events,
+            [
+                VtxEvent::VersionChanged {
+                    from: Version::V2_0,
+                    to: Version::V2_1
+                },
+                VtxEvent::PowerChanged {
+                    from: Power::Level(0),
+                    to: Power::dBm(14)
+                },
+            ]
+        );
+    }
+}
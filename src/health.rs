@@ -0,0 +1,397 @@
+use crate::parser::SmartAudioError;
+use crate::parser::State;
+
+/// Number of most recent transactions used for statistics and classification.
+pub const HEALTH_WINDOW: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Outcome {
+    /// Response received, with latency in milliseconds.
+    Response(u32),
+    Timeout,
+    CrcError,
+    /// Malformed frame after a valid header, such as an unexpected length.
+    FrameError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkQuality {
+    Good,
+    Degraded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HealthThresholds {
+    /// Failed transactions in the window, in percent, that degrade the link.
+    pub degraded_failure_rate: u8,
+    /// Failed transactions in the window, in percent, that fail the link.
+    pub failed_failure_rate: u8,
+    /// Consecutive failed transactions that fail the link regardless of rate.
+    pub failed_consecutive: u8,
+    /// 90th percentile latency, in milliseconds, that degrades the link.
+    pub degraded_latency: u32,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            degraded_failure_rate: 10,
+            failed_failure_rate: 50,
+            failed_consecutive: 3,
+            degraded_latency: 200,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkTotals {
+    pub responses: u32,
+    pub timeouts: u32,
+    pub crc_errors: u32,
+    pub frame_errors: u32,
+}
+
+/// Latency and error statistics of the host to VTX link.
+///
+/// Transactions are timed from the end of transmission ([`Self::tx_complete`])
+/// to the completed response ([`Self::response_received`]). Statistics cover
+/// the last [`HEALTH_WINDOW`] transactions, totals cover the whole lifetime.
+#[derive(Debug, Clone)]
+pub struct LinkHealth {
+    thresholds: HealthThresholds,
+    window: [Option<Outcome>; HEALTH_WINDOW],
+    head: usize,
+    consecutive_failures: u8,
+    pending_since: Option<u32>,
+    totals: LinkTotals,
+}
+
+impl LinkHealth {
+    pub fn new(thresholds: HealthThresholds) -> Self {
+        Self {
+            thresholds,
+            window: [None; HEALTH_WINDOW],
+            head: 0,
+            consecutive_failures: 0,
+            pending_since: None,
+            totals: LinkTotals::default(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new(self.thresholds);
+    }
+
+    pub fn totals(&self) -> LinkTotals {
+        self.totals
+    }
+
+    /// Starts timing a transaction once the last command byte left the wire.
+    pub fn tx_complete(&mut self, now: u32) {
+        self.pending_since = Some(now);
+    }
+
+    /// Completes the pending transaction and returns its latency.
+    pub fn response_received(&mut self, now: u32) -> Option<u32> {
+        let latency = now.wrapping_sub(self.pending_since.take()?);
+        self.record(Outcome::Response(latency));
+        Some(latency)
+    }
+
+    /// Records the pending transaction as timed out once `timeout` elapsed.
+    pub fn poll_timeout(&mut self, now: u32, timeout: u32) -> bool {
+        match self.pending_since {
+            Some(since) if now.wrapping_sub(since) >= timeout => {
+                self.pending_since = None;
+                self.record(Outcome::Timeout);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Records a parser error for the pending transaction and returns
+    /// whether it ended the transaction.
+    ///
+    /// Only CRC and length errors of a frame whose header was seen count.
+    /// Bytes skipped while hunting for a header, such as line noise or the
+    /// echo of the command on a single-wire line, leave the transaction
+    /// pending, as do errors while no transaction is pending.
+    pub fn parser_error(&mut self, error: &SmartAudioError) -> bool {
+        let outcome = match error {
+            SmartAudioError::InvalidCrc { .. } => Outcome::CrcError,
+            SmartAudioError::UnexpetedDataForState(State::AwaitingLength, _)
+            | SmartAudioError::InvalidPayloadLength
+            | SmartAudioError::UnknownCommand(_) => Outcome::FrameError,
+            _ => return false,
+        };
+        if self.pending_since.take().is_none() {
+            return false;
+        }
+        self.record(outcome);
+        true
+    }
+
+    pub fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Response(_) => {
+                self.totals.responses = self.totals.responses.saturating_add(1);
+                self.consecutive_failures = 0;
+            }
+            Outcome::Timeout => self.totals.timeouts = self.totals.timeouts.saturating_add(1),
+            Outcome::CrcError => self.totals.crc_errors = self.totals.crc_errors.saturating_add(1),
+            Outcome::FrameError => {
+                self.totals.frame_errors = self.totals.frame_errors.saturating_add(1)
+            }
+        }
+        if !matches!(outcome, Outcome::Response(_)) {
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+        self.window[self.head] = Some(outcome);
+        self.head = (self.head + 1) % HEALTH_WINDOW;
+    }
+
+    fn outcomes(&self) -> impl Iterator<Item = Outcome> + '_ {
+        self.window.iter().flatten().copied()
+    }
+
+    fn latencies(&self) -> impl Iterator<Item = u32> + '_ {
+        self.outcomes().filter_map(|o| match o {
+            Outcome::Response(latency) => Some(latency),
+            _ => None,
+        })
+    }
+
+    /// Number of transactions currently in the window.
+    pub fn samples(&self) -> usize {
+        self.outcomes().count()
+    }
+
+    pub fn min_latency(&self) -> Option<u32> {
+        self.latencies().min()
+    }
+
+    pub fn max_latency(&self) -> Option<u32> {
+        self.latencies().max()
+    }
+
+    pub fn avg_latency(&self) -> Option<u32> {
+        let (count, sum) = self
+            .latencies()
+            .fold((0u64, 0u64), |(c, s), l| (c + 1, s + u64::from(l)));
+        if count == 0 {
+            return None;
+        }
+        u32::try_from(sum / count).ok()
+    }
+
+    /// Nearest-rank latency percentile, `percentile` is clamped to `0..=100`.
+    pub fn latency_percentile(&self, percentile: u8) -> Option<u32> {
+        let mut sorted = [0u32; HEALTH_WINDOW];
+        let mut count = 0;
+        for latency in self.latencies() {
+            sorted[count] = latency;
+            count += 1;
+        }
+        if count == 0 {
+            return None;
+        }
+        let sorted = &mut sorted[..count];
+        sorted.sort_unstable();
+        let rank = (usize::from(percentile.min(100)) * count).div_ceil(100);
+        Some(sorted[rank.saturating_sub(1)])
+    }
+
+    fn rate(&self, predicate: impl Fn(&Outcome) -> bool) -> u8 {
+        let total = self.samples();
+        if total == 0 {
+            return 0;
+        }
+        let matching = self.outcomes().filter(|o| predicate(o)).count();
+        (matching * 100 / total) as u8
+    }
+
+    /// Timed out transactions in the window, in percent.
+    pub fn timeout_rate(&self) -> u8 {
+        self.rate(|o| *o == Outcome::Timeout)
+    }
+
+    /// Transactions answered with a bad CRC in the window, in percent.
+    pub fn crc_error_rate(&self) -> u8 {
+        self.rate(|o| *o == Outcome::CrcError)
+    }
+
+    /// Transactions without a valid response in the window, in percent.
+    pub fn failure_rate(&self) -> u8 {
+        self.rate(|o| !matches!(o, Outcome::Response(_)))
+    }
+
+    /// Timeout covering the slowest observed responses with a 50% margin,
+    /// useful to tune retry behavior.
+    pub fn suggested_timeout(&self) -> Option<u32> {
+        self.latency_percentile(99)
+            .map(|latency| latency.saturating_add(latency / 2))
+    }
+
+    pub fn quality(&self) -> LinkQuality {
+        let thresholds = &self.thresholds;
+        let failure_rate = self.failure_rate();
+        if self.consecutive_failures >= thresholds.failed_consecutive
+            || (self.samples() > 0 && failure_rate >= thresholds.failed_failure_rate)
+        {
+            return LinkQuality::Failed;
+        }
+        let slow = self
+            .latency_percentile(90)
+            .is_some_and(|latency| latency > thresholds.degraded_latency);
+        if slow || failure_rate >= thresholds.degraded_failure_rate {
+            return LinkQuality::Degraded;
+        }
+        LinkQuality::Good
+    }
+}
+
+impl Default for LinkHealth {
+    fn default() -> Self {
+        Self::new(HealthThresholds::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_statistics() {
+        let mut health = LinkHealth::default();
+        for (start, latency) in [(0, 80), (1000, 100), (2000, 120), (3000, 90)] {
+            health.tx_complete(start);
+            assert_eq!(health.response_received(start + latency), Some(latency));
+        }
+        assert_eq!(health.min_latency(), Some(80));
+        assert_eq!(health.max_latency(), Some(120));
+        assert_eq!(health.avg_latency(), Some(97));
+        assert_eq!(health.latency_percentile(50), Some(90));
+        assert_eq!(health.latency_percentile(100), Some(120));
+        assert_eq!(health.suggested_timeout(), Some(180));
+        assert_eq!(health.quality(), LinkQuality::Good);
+    }
+
+    #[test]
+    fn test_response_without_transaction_is_ignored() {
+        let mut health = LinkHealth::default();
+        assert_eq!(health.response_received(10), None);
+        assert_eq!(health.samples(), 0);
+        assert_eq!(health.latency_percentile(50), None);
+    }
+
+    #[test]
+    fn test_noise_while_idle_is_ignored() {
+        let mut health = LinkHealth::default();
+        assert!(!health.parser_error(&SmartAudioError::InvalidHeader));
+        health.tx_complete(0);
+        health.response_received(100);
+        assert!(!health.parser_error(&SmartAudioError::InvalidCrc {
+            calculated_crc: 0,
+            frame_crc: 1,
+        }));
+        assert_eq!(health.samples(), 1);
+        assert_eq!(health.failure_rate(), 0);
+        assert_eq!(health.totals().crc_errors, 0);
+    }
+
+    #[test]
+    fn test_garbage_keeps_transaction_pending() {
+        let mut health = LinkHealth::default();
+        health.tx_complete(0);
+        // A leading zero, then a header byte followed by garbage.
+        for (state, byte) in [
+            (State::AwaitingHeader1, 0x00),
+            (State::AwaitingHeader2, 0x03),
+        ] {
+            let error = SmartAudioError::UnexpetedDataForState(state, byte);
+            assert!(!health.parser_error(&error));
+        }
+        assert!(!health.parser_error(&SmartAudioError::InvalidHeader));
+        assert_eq!(health.response_received(80), Some(80));
+        assert_eq!(health.totals().frame_errors, 0);
+        assert_eq!(health.failure_rate(), 0);
+
+        health.tx_complete(1000);
+        let error = SmartAudioError::UnexpetedDataForState(State::AwaitingLength, 0xFF);
+        assert!(health.parser_error(&error));
+        assert_eq!(health.response_received(1080), None);
+        assert_eq!(health.totals().frame_errors, 1);
+    }
+
+    #[test]
+    fn test_timeouts_and_crc_errors() {
+        let mut health = LinkHealth::default();
+        for i in 0..8 {
+            health.tx_complete(i * 1000);
+            health.response_received(i * 1000 + 100);
+        }
+        health.tx_complete(10_000);
+        assert!(!health.poll_timeout(10_100, 200));
+        assert!(health.poll_timeout(10_200, 200));
+        health.tx_complete(11_000);
+        health.parser_error(&SmartAudioError::InvalidCrc {
+            calculated_crc: 0,
+            frame_crc: 1,
+        });
+
+        assert_eq!(health.timeout_rate(), 10);
+        assert_eq!(health.crc_error_rate(), 10);
+        assert_eq!(health.failure_rate(), 20);
+        assert_eq!(health.quality(), LinkQuality::Degraded);
+        assert_eq!(
+            health.totals(),
+            LinkTotals {
+                responses: 8,
+                timeouts: 1,
+                crc_errors: 1,
+                frame_errors: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_consecutive_failures_fail_link() {
+        let mut health = LinkHealth::default();
+        for _ in 0..20 {
+            health.record(Outcome::Response(50));
+        }
+        for _ in 0..3 {
+            health.record(Outcome::Timeout);
+        }
+        assert_eq!(health.quality(), LinkQuality::Failed);
+        health.record(Outcome::Response(50));
+        assert_eq!(health.quality(), LinkQuality::Degraded);
+    }
+
+    #[test]
+    fn test_slow_link_is_degraded() {
+        let mut health = LinkHealth::default();
+        for _ in 0..10 {
+            health.record(Outcome::Response(300));
+        }
+        assert_eq!(health.quality(), LinkQuality::Degraded);
+    }
+
+    #[test]
+    fn test_window_drops_old_samples() {
+        let mut health = LinkHealth::default();
+        health.record(Outcome::Timeout);
+        for _ in 0..HEALTH_WINDOW {
+            health.record(Outcome::Response(10));
+        }
+        assert_eq!(health.samples(), HEALTH_WINDOW);
+        assert_eq!(health.timeout_rate(), 0);
+        assert_eq!(health.totals().timeouts, 1);
+    }
+}
@@ -0,0 +1,295 @@
+use crate::commands::Command;
+use crate::parser::FrameOrigin;
+use crate::parser::ParserConfig;
+use crate::parser::RawSmartAudioFrame;
+use crate::parser::SmartAudioError;
+use crate::parser::SmartAudioParser;
+use crate::responses::Response;
+
+/// Bytes written as hex text, as found in firmware logs and forum posts.
+///
+/// Text is split into words of letters and digits, everything else is a
+/// separator, so `AA 55 03`, `0xAA,0x55,0x03`, `aa:55:03`, `\xAA\x55\x03`
+/// and `AA5503` all give the same bytes. A word holds bytes when it is hex
+/// with an even number of digits, or up to two digits after a `0x` or `\x`
+/// prefix. Such words next to each other on a line form a run, which is
+/// used only if it has a decimal digit or a prefix, so words like `be` or
+/// `dead` in log text are skipped. A leading timestamp or offset column,
+/// like `[12:00:01]`, `12:00:01.250` or `00000010:`, is skipped as well,
+/// which also skips a line of all decimal bytes joined by `:`.
+#[derive(Debug, Clone)]
+pub struct HexBytes<'a> {
+    /// Lines not looked at yet.
+    text: &'a [u8],
+    /// Rest of the current line.
+    line: &'a [u8],
+    /// Rest of the current run, every word in it holds bytes.
+    run: &'a [u8],
+    /// Hex digits of the current word not yet returned.
+    digits: &'a [u8],
+}
+
+impl<'a> HexBytes<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self {
+            text: text.as_bytes(),
+            line: &[],
+            run: &[],
+            digits: &[],
+        }
+    }
+
+    /// Hex digits of the next word of a usable run.
+    fn next_word(&mut self) -> Option<&'a [u8]> {
+        loop {
+            let (separators, word, rest) = split_word(self.run);
+            if let Some((digits, _)) = hex_digits(separators, word) {
+                self.run = rest;
+                return Some(digits);
+            }
+            self.run = self.next_run()?;
+        }
+    }
+
+    /// Finds the next run with a decimal digit or a prefix.
+    fn next_run(&mut self) -> Option<&'a [u8]> {
+        loop {
+            if self.line.is_empty() {
+                if self.text.is_empty() {
+                    return None;
+                }
+                let end = self
+                    .text
+                    .iter()
+                    .position(|c| *c == b'\n')
+                    .map_or(self.text.len(), |newline| newline + 1);
+                let (line, text) = self.text.split_at(end);
+                self.text = text;
+                self.line = skip_column(line);
+            }
+
+            let start = self.line;
+            let mut len = 0;
+            let mut marked = false;
+            loop {
+                let (separators, word, rest) = split_word(self.line);
+                self.line = rest;
+                let Some((digits, prefixed)) = hex_digits(separators, word) else {
+                    break;
+                };
+                marked |= prefixed || digits.iter().any(u8::is_ascii_digit);
+                len = start.len() - rest.len();
+            }
+            if marked {
+                return Some(&start[..len]);
+            }
+        }
+    }
+}
+
+/// Splits `text` into the separators before its first word, the word and
+/// the rest.
+fn split_word(text: &[u8]) -> (&[u8], &[u8], &[u8]) {
+    let start = text
+        .iter()
+        .position(u8::is_ascii_alphanumeric)
+        .unwrap_or(text.len());
+    let (separators, rest) = text.split_at(start);
+    let len = rest
+        .iter()
+        .position(|c| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    let (word, rest) = rest.split_at(len);
+    (separators, word, rest)
+}
+
+/// Hex digits of `word` if it holds bytes, and whether they follow a prefix.
+fn hex_digits<'a>(separators: &[u8], word: &'a [u8]) -> Option<(&'a [u8], bool)> {
+    let escaped = separators.last() == Some(&b'\\');
+    let (prefixed, digits) = match word {
+        [b'0', b'x' | b'X', digits @ ..] => (true, digits),
+        [b'x', digits @ ..] if escaped => (true, digits),
+        _ => (false, word),
+    };
+    let usable = !digits.is_empty()
+        && digits.iter().all(u8::is_ascii_hexdigit)
+        && (digits.len() % 2 == 0 || (prefixed && digits.len() == 1));
+    usable.then_some((digits, prefixed))
+}
+
+/// Skips a leading `[..]` group, timestamp or column ending with `:`.
+fn skip_column(line: &[u8]) -> &[u8] {
+    let line = line.trim_ascii_start();
+    if let Some(rest) = line.strip_prefix(b"[") {
+        return rest
+            .iter()
+            .position(|c| *c == b']')
+            .map_or(line, |end| &rest[end + 1..]);
+    }
+    let len = line
+        .iter()
+        .position(u8::is_ascii_whitespace)
+        .unwrap_or(line.len());
+    let (column, rest) = line.split_at(len);
+    let timestamp = column.iter().any(|c| matches!(c, b':' | b'.'))
+        && column
+            .iter()
+            .all(|c| c.is_ascii_digit() || matches!(c, b':' | b'.'));
+    if timestamp || column.ends_with(b":") {
+        rest
+    } else {
+        line
+    }
+}
+
+fn digit(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => c - b'A' + 10,
+    }
+}
+
+impl Iterator for HexBytes<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.digits.is_empty() {
+            self.digits = self.next_word()?;
+        }
+        let (&first, rest) = self.digits.split_first()?;
+        let (byte, rest) = match rest.split_first() {
+            Some((&low, rest)) => (digit(first) << 4 | digit(low), rest),
+            // Single digit after a prefix, e.g. `0x9`.
+            None => (digit(first), rest),
+        };
+        self.digits = rest;
+        Some(byte)
+    }
+}
+
+/// Frame from either side of the link.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Decoded {
+    Command(Command),
+    Response(Response),
+}
+
+impl Decoded {
+    pub fn parse(raw_frame: &RawSmartAudioFrame<'_>) -> Result<Self, SmartAudioError> {
+        match raw_frame.origin() {
+            FrameOrigin::Host => Command::parse(raw_frame).map(Self::Command),
+            FrameOrigin::Vtx => Response::parse(raw_frame).map(Self::Response),
+        }
+    }
+}
+
+/// Decodes the host and VTX frames written as hex in `text`.
+///
+/// Bytes outside frames are skipped, a corrupted frame is reported as an
+/// error.
+pub fn decode_hex(text: &str) -> impl Iterator<Item = Result<Decoded, SmartAudioError>> + '_ {
+    let mut parser: SmartAudioParser = SmartAudioParser::with_config(ParserConfig::ANY);
+    let mut bytes = HexBytes::new(text);
+    core::iter::from_fn(move || {
+        for byte in bytes.by_ref() {
+            match parser.push_byte_raw(byte) {
+                Ok(Some(frame)) => return Some(Decoded::parse(&frame)),
+                Ok(None) | Err(SmartAudioError::UnexpetedDataForState(..)) => (),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        None
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::commands::GetSettingsCommand;
+    use crate::commands::SetChannelCommand;
+    use crate::commands::SetFrequencyCommand;
+    use std::vec::Vec;
+
+    const GET_SETTINGS: [u8; 5] = [0xAA, 0x55, 0x03, 0x00, 0x9F];
+    const SETTINGS: [u8; 10] = [0xAA, 0x55, 0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9, 0x0A];
+
+    #[test]
+    fn test_separators_and_prefixes() {
+        for text in [
+            "AA 55 09 06 01 00 1A 16 E9 0A",
+            "0xAA,0x55,0x09,0x06,0x01,0x00,0x1A,0x16,0xE9,0x0A",
+            "{0xaa, 0x55, 0x9, 0x6, 0x1, 0x0, 0x1a, 0x16, 0xe9, 0xa};",
+            "aa:55:09:06:01:00:1a:16:e9:0a",
+            "\\xAA\\x55\\x09\\x06\\x01\\x00\\x1A\\x16\\xE9\\x0A",
+            "AA5509060100 1A16E90A",
+            "SA: rx AA-55-09-06-01-00-1A-16-E9-0A done",
+        ] {
+            let bytes: Vec<_> = HexBytes::new(text).collect();
+            assert_eq!(bytes, SETTINGS, "{text}");
+        }
+        assert_eq!(HexBytes::new("bad 0x123 xAA").count(), 0);
+    }
+
+    #[test]
+    fn test_log_lines() {
+        for (line, expected) in [
+            ("[12:00:01] SA TX: AA 55 03 00 9F", &GET_SETTINGS[..]),
+            ("12:00:01.250 SA TX AA 55 03 00 9F", &GET_SETTINGS),
+            ("00000010: aa 55 03 00 9f", &GET_SETTINGS),
+            ("VTX be dead, add AA 55 03 00 9F", &GET_SETTINGS),
+            ("sa: rx aa:55:09:06:01:00:1a:16:e9:0a", &SETTINGS),
+            ("[12:00:03] deadbeef done", &[]),
+        ] {
+            let bytes: Vec<_> = HexBytes::new(line).collect();
+            assert_eq!(bytes, expected, "{line}");
+        }
+        let bytes: Vec<_> = HexBytes::new("[1] AA 55 03\n[2] 00 9F\n").collect();
+        assert_eq!(bytes, GET_SETTINGS);
+    }
+
+    #[test]
+    fn test_decode_log() {
+        let log = "\
+            [12:00:01] SA TX: AA 55 03 00 9F\n\
+            [12:00:01] SA RX: 00 AA 55 09 06 01 00 1A 16 E9 0A\n\
+            [12:00:02] SA RX: AA 55 09 06 01 00 1A 16 E9 0B\n\
+            [12:00:03] done\n";
+        let decoded: Vec<_> = decode_hex(log).collect();
+        assert_eq!(
+            decoded[0],
+            Ok(Decoded::Command(Command::GetSettings(
+                GetSettingsCommand {}
+            )))
+        );
+        assert!(matches!(
+            decoded[1],
+            Ok(Decoded::Response(Response::GetSettings(settings))) if settings.frequency == 5865
+        ));
+        assert!(matches!(
+            decoded[2],
+            Err(SmartAudioError::InvalidCrc { .. })
+        ));
+        assert_eq!(decoded.len(), 3);
+    }
+
+    #[test]
+    fn test_decode_commands_with_vtx_crc_collisions() {
+        // 0xA0, the low byte of 5792, is the VTX style CRC of `09 02 16`.
+        let log = "SA TX: AA 55 09 02 16 A0 BD\nSA TX: AA 55 07 01 E4 90\n";
+        let decoded: Vec<_> = decode_hex(log).collect();
+        assert_eq!(
+            decoded,
+            [
+                Ok(Decoded::Command(Command::SetFrequency(
+                    SetFrequencyCommand { frequency: 5792 }
+                ))),
+                Ok(Decoded::Command(Command::SetChannel(SetChannelCommand {
+                    channel: 0xE4
+                }))),
+            ]
+        );
+    }
+}
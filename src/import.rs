@@ -0,0 +1,414 @@
+use std::io::BufRead;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::capture::Capture;
+use crate::capture::CaptureError;
+use crate::capture::Direction;
+use crate::soft_uart::SoftUartConfig;
+use crate::soft_uart::SoftUartRx;
+use crate::timing::wire_time_us;
+use crate::timing::BITS_PER_BYTE;
+
+/// Timestamps of edge captures are decoded with a 1 MHz clock.
+const TICK_HZ: u32 = 1_000_000;
+
+/// Comma separated rows, with the column names of the header row if any.
+struct Csv<R> {
+    lines: std::io::Lines<R>,
+    line: usize,
+    header: Option<Vec<String>>,
+    pending: Option<Vec<String>>,
+}
+
+fn split_row(line: &str) -> Vec<String> {
+    line.split(',')
+        .map(|field| field.trim().trim_matches('"').trim().into())
+        .collect()
+}
+
+impl<R: BufRead> Csv<R> {
+    fn new(reader: R) -> Result<Self, CaptureError> {
+        let mut csv = Self {
+            lines: reader.lines(),
+            line: 0,
+            header: None,
+            pending: None,
+        };
+        if let Some(row) = csv.row()? {
+            // Header rows do not start with a timestamp.
+            if row[0].parse::<f64>().is_ok() {
+                csv.pending = Some(row);
+            } else {
+                csv.header = Some(row);
+            }
+        }
+        Ok(csv)
+    }
+
+    /// Next row, skipping blank lines and `;` or `#` comments.
+    fn row(&mut self) -> Result<Option<Vec<String>>, CaptureError> {
+        if let Some(row) = self.pending.take() {
+            return Ok(Some(row));
+        }
+        for line in self.lines.by_ref() {
+            let line = line?;
+            self.line += 1;
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with(';') && !line.starts_with('#') {
+                return Ok(Some(split_row(line)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Indexes of the columns whose lowercase name contains one of `names`.
+    fn columns(&self, names: &[&str]) -> Vec<usize> {
+        let Some(header) = &self.header else {
+            return Vec::new();
+        };
+        (0..header.len())
+            .filter(|i| {
+                let column = header[*i].to_ascii_lowercase();
+                names.iter().any(|name| column.contains(name))
+            })
+            .collect()
+    }
+
+    /// Index of the first column whose lowercase name contains one of `names`.
+    fn column(&self, names: &[&str]) -> Option<usize> {
+        self.columns(names).first().copied()
+    }
+
+    /// Index of a column that must be named in the header row, or
+    /// `position` without a header row.
+    fn required_column(&self, names: &[&str], position: usize) -> Result<usize, CaptureError> {
+        match self.header {
+            Some(_) => self.column(names).ok_or(CaptureError::Format(1)),
+            None => Ok(position),
+        }
+    }
+
+    fn error(&self) -> CaptureError {
+        CaptureError::Format(self.line)
+    }
+}
+
+fn parse_seconds(field: &str) -> Option<f64> {
+    field
+        .parse()
+        .ok()
+        .filter(|seconds: &f64| seconds.is_finite())
+}
+
+fn parse_byte(field: &str) -> Option<u8> {
+    let digits = field
+        .strip_prefix("0x")
+        .or_else(|| field.strip_prefix("0X"))
+        .unwrap_or(field);
+    u8::from_str_radix(digits, 16).ok()
+}
+
+fn is_error(field: Option<&String>) -> bool {
+    field.is_some_and(|field| !matches!(field.to_ascii_lowercase().as_str(), "" | "0" | "false"))
+}
+
+fn note_dropped(capture: &mut Capture, dropped: usize) {
+    if dropped > 0 {
+        capture.notes = std::format!("{dropped} bytes with errors dropped");
+    }
+}
+
+/// Appends `byte` started at `timestamp_us`, to the last chunk when it
+/// follows it back to back on the wire.
+fn push_byte(capture: &mut Capture, timestamp_us: u64, byte: u8) {
+    let baud = capture.baud;
+    if let Some(chunk) = capture.chunks.last_mut() {
+        let expected = chunk.timestamp_us + u64::from(wire_time_us(baud, chunk.bytes.len()));
+        let slack = u64::from(wire_time_us(baud, 1) / 2);
+        if timestamp_us.abs_diff(expected) <= slack {
+            chunk.bytes.push(byte);
+            return;
+        }
+    }
+    capture.push(timestamp_us, Direction::Unknown, &[byte]);
+}
+
+/// Imports an async serial analyzer export, one decoded byte per row.
+///
+/// Columns are found by name: the time in seconds (`Time [s]` or
+/// `start_time`), the byte (`Value` or `data`) as hex with or without `0x`
+/// and any number of framing or parity error flags, every column with
+/// `error` in its name. Without a header row the columns are time, byte and
+/// error. Bytes with an error are dropped and counted in the notes.
+/// Timestamps start at the first row, bytes sent back to back are merged
+/// into one chunk.
+pub fn import_serial_csv<R: BufRead>(reader: R, baud: u32) -> Result<Capture, CaptureError> {
+    let mut csv = Csv::new(reader)?;
+    let time = csv.required_column(&["time"], 0)?;
+    let data = csv.required_column(&["value", "data"], 1)?;
+    let errors = match csv.header {
+        Some(_) => csv.columns(&["error"]),
+        None => std::vec![2],
+    };
+
+    let mut capture = Capture::new(baud, "");
+    let mut start = None;
+    let mut dropped = 0;
+    while let Some(row) = csv.row()? {
+        let seconds = row.get(time).and_then(|field| parse_seconds(field));
+        let byte = row.get(data).and_then(|field| parse_byte(field));
+        let (Some(seconds), Some(byte)) = (seconds, byte) else {
+            return Err(csv.error());
+        };
+        if errors.iter().any(|error| is_error(row.get(*error))) {
+            dropped += 1;
+            continue;
+        }
+        let start = *start.get_or_insert(seconds);
+        push_byte(&mut capture, ((seconds - start) * 1e6).round() as u64, byte);
+    }
+    note_dropped(&mut capture, dropped);
+    Ok(capture)
+}
+
+/// Imports a digital export of the line, one row per edge or per sample.
+///
+/// The time column is in seconds and `channel` names the level column, the
+/// first column after the time by default. Rows that repeat the previous
+/// level are skipped, so both edge and sample exports work. Bytes are
+/// decoded with [`SoftUartRx`] and stamped with their start edge, bytes with
+/// a framing error are dropped and counted in the notes.
+pub fn import_edge_csv<R: BufRead>(
+    reader: R,
+    baud: u32,
+    inverted: bool,
+    channel: Option<&str>,
+) -> Result<Capture, CaptureError> {
+    let mut csv = Csv::new(reader)?;
+    let time = csv.required_column(&["time"], 0)?;
+    let level = match channel {
+        Some(channel) => csv
+            .column(&[channel.to_ascii_lowercase().as_str()])
+            .ok_or(CaptureError::Format(1))?,
+        None => time + 1,
+    };
+
+    let mut rx = SoftUartRx::new(SoftUartConfig {
+        baud,
+        tick_hz: TICK_HZ,
+        inverted,
+    });
+    let mut capture = Capture::new(baud, "");
+    let mut start = None;
+    let mut previous = None;
+    let mut last_tick = 0;
+    let mut dropped = 0;
+    while let Some(row) = csv.row()? {
+        let seconds = row.get(time).and_then(|field| parse_seconds(field));
+        let high = row.get(level).and_then(|field| match field.as_str() {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        });
+        let (Some(seconds), Some(high)) = (seconds, high) else {
+            return Err(csv.error());
+        };
+        let start = *start.get_or_insert(seconds);
+        let tick = ((seconds - start) * f64::from(TICK_HZ)).round() as u64;
+        if previous.replace(high) == Some(high) {
+            continue;
+        }
+        last_tick = tick;
+        let since = rx.receiving_since();
+        match (rx.push_edge(high, tick as u32), since) {
+            (Some(Ok(byte)), Some(since)) => {
+                push_byte(&mut capture, unwrap_tick(since, tick), byte);
+            }
+            (Some(Err(_)), _) => dropped += 1,
+            _ => (),
+        }
+    }
+    let end = last_tick + 2 * u64::from(BITS_PER_BYTE) * u64::from(TICK_HZ / baud.max(1));
+    let since = rx.receiving_since();
+    match (rx.poll(end as u32), since) {
+        (Some(Ok(byte)), Some(since)) => push_byte(&mut capture, unwrap_tick(since, end), byte),
+        (Some(Err(_)), _) => dropped += 1,
+        _ => (),
+    }
+    note_dropped(&mut capture, dropped);
+    Ok(capture)
+}
+
+/// Widens a wrapped 32-bit tick that is at most one wrap before `now`.
+fn unwrap_tick(tick: u32, now: u64) -> u64 {
+    now - u64::from((now as u32).wrapping_sub(tick))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::Command;
+    use crate::responses::Response;
+    use crate::sniffer::Exchange;
+    use crate::sniffer::Sniffer;
+    use std::fmt::Write;
+
+    const GET_SETTINGS: [u8; 5] = [0xAA, 0x55, 0x03, 0x00, 0x9F];
+    const SETTINGS: [u8; 10] = [0xAA, 0x55, 0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9, 0x0A];
+
+    fn transcript(capture: &Capture) -> Vec<Exchange> {
+        let mut sniffer = Sniffer::default();
+        capture.replay().sniff(&mut sniffer).collect()
+    }
+
+    fn assert_transaction(capture: &Capture) {
+        assert!(matches!(
+            transcript(capture)[..],
+            [Exchange::Transaction {
+                command: Command::GetSettings(_),
+                response: Response::GetSettings(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_saleae_serial_export() {
+        let mut csv = String::from("name,type,start_time,duration,\"data\",\"error\"\n");
+        let mut t = 1.5;
+        for byte in GET_SETTINGS {
+            writeln!(
+                csv,
+                "\"Async Serial\",\"data\",{t:.7},0.0022917,0x{byte:02X},"
+            )
+            .unwrap();
+            t += 11.0 / 4800.0;
+        }
+        writeln!(
+            csv,
+            "\"Async Serial\",\"data\",1.53,0.0022917,0x00,\"framing\""
+        )
+        .unwrap();
+        t = 1.55;
+        for byte in SETTINGS {
+            writeln!(
+                csv,
+                "\"Async Serial\",\"data\",{t:.7},0.0022917,0x{byte:02X},"
+            )
+            .unwrap();
+            t += 11.0 / 4800.0;
+        }
+
+        let capture = import_serial_csv(csv.as_bytes(), 4800).unwrap();
+        assert_eq!(capture.chunks.len(), 2);
+        assert_eq!(capture.chunks[0].timestamp_us, 0);
+        assert_eq!(capture.chunks[0].bytes, GET_SETTINGS);
+        assert_eq!(capture.chunks[1].timestamp_us, 50_000);
+        assert_eq!(capture.notes, "1 bytes with errors dropped");
+        assert_transaction(&capture);
+    }
+
+    #[test]
+    fn test_sigrok_serial_export_without_header() {
+        let mut csv = String::from("; sigrok uart rx\n");
+        for (i, byte) in GET_SETTINGS.iter().chain(&SETTINGS).enumerate() {
+            writeln!(csv, "{:.6},{byte:02x}", i as f64 * 0.0023).unwrap();
+        }
+        let capture = import_serial_csv(csv.as_bytes(), 4800).unwrap();
+        assert_eq!(capture.chunks.len(), 1);
+        assert_transaction(&capture);
+
+        let bad = "Time [s],Value\n0.1,0xZZ\n";
+        assert!(matches!(
+            import_serial_csv(bad.as_bytes(), 4800),
+            Err(CaptureError::Format(2))
+        ));
+    }
+
+    #[test]
+    fn test_logic_1_export_error_columns() {
+        let mut csv = String::from("Time [s],Value,Parity Error,Framing Error\n");
+        for (i, byte) in GET_SETTINGS.iter().enumerate() {
+            writeln!(csv, "{:.7},0x{byte:02X},,", 1.0 + i as f64 * 0.0023).unwrap();
+        }
+        csv.push_str("1.03,0x00,,Error\n1.04,0x00,Error,\n");
+        let capture = import_serial_csv(csv.as_bytes(), 4800).unwrap();
+        assert_eq!(capture.chunks.len(), 1);
+        assert_eq!(capture.chunks[0].bytes, GET_SETTINGS);
+        assert_eq!(capture.notes, "2 bytes with errors dropped");
+
+        // Named columns without an error flag, no positional fallback.
+        let csv = "Time [s],Value,Channel\n0.1,0xAA,2\n0.1023,0x55,2\n";
+        let capture = import_serial_csv(csv.as_bytes(), 4800).unwrap();
+        assert_eq!(capture.chunks[0].bytes, [0xAA, 0x55]);
+        assert!(capture.notes.is_empty());
+
+        let csv = "Time [s],Channel\n0.1,2\n";
+        assert!(matches!(
+            import_serial_csv(csv.as_bytes(), 4800),
+            Err(CaptureError::Format(1))
+        ));
+    }
+
+    /// Level changes of `bytes` sent back to back from `t`, stop bits low
+    /// for a framing error when `stop` is false, then idle.
+    fn write_edges(csv: &mut String, t: &mut f64, level: &mut bool, bytes: &[u8], stop: bool) {
+        let bit = 1.0 / 4800.0;
+        for byte in bytes {
+            let mut bits = [stop; 11];
+            bits[0] = false;
+            for (i, bit) in bits.iter_mut().enumerate().skip(1).take(8) {
+                *bit = byte & (1 << (i - 1)) != 0;
+            }
+            for value in bits {
+                if value != *level {
+                    *level = value;
+                    writeln!(csv, "{t:.9},{}", u8::from(*level)).unwrap();
+                }
+                *t += bit;
+            }
+        }
+        if !*level {
+            // Back to idle after a low stop bit.
+            *level = true;
+            writeln!(csv, "{t:.9},1").unwrap();
+        }
+    }
+
+    #[test]
+    fn test_edge_export() {
+        let mut csv = String::from("Time [s],Channel 0\n0.000000000,1\n");
+        let mut t = 0.01;
+        let mut level = true;
+        write_edges(&mut csv, &mut t, &mut level, &GET_SETTINGS, true);
+        t += 0.03;
+        write_edges(&mut csv, &mut t, &mut level, &SETTINGS, true);
+
+        let capture = import_edge_csv(csv.as_bytes(), 4800, false, Some("channel 0")).unwrap();
+        let bytes: Vec<u8> = capture.replay().map(|byte| byte.byte).collect();
+        assert_eq!(bytes, [&GET_SETTINGS[..], &SETTINGS[..]].concat());
+        assert_eq!(capture.chunks.len(), 2);
+        assert_eq!(capture.chunks[0].timestamp_us, 10_000);
+        assert!(capture.notes.is_empty());
+        assert_transaction(&capture);
+    }
+
+    #[test]
+    fn test_edge_export_framing_errors() {
+        let mut csv = String::from("Time [s],Channel 0\n0.000000000,1\n");
+        let mut t = 0.01;
+        let mut level = true;
+        write_edges(&mut csv, &mut t, &mut level, &GET_SETTINGS, true);
+        t += 0.01;
+        write_edges(&mut csv, &mut t, &mut level, &[0x0F], false);
+        t += 0.01;
+        write_edges(&mut csv, &mut t, &mut level, &SETTINGS, true);
+
+        let capture = import_edge_csv(csv.as_bytes(), 4800, false, None).unwrap();
+        let bytes: Vec<u8> = capture.replay().map(|byte| byte.byte).collect();
+        assert_eq!(bytes, [&GET_SETTINGS[..], &SETTINGS[..]].concat());
+        assert_eq!(capture.notes, "1 bytes with errors dropped");
+        assert_transaction(&capture);
+    }
+}
@@ -0,0 +1,518 @@
+use crate::parser::FrameOrigin;
+use crate::parser::SmartAudioError;
+
+/// How a field is stored on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FieldKind {
+    /// Protocol version, carried by the command byte.
+    Version,
+    U8,
+    /// Big endian.
+    U16,
+    /// Power level, or dBm with the MSB set.
+    Power,
+    /// Single bit of a flags byte.
+    Flag(u8),
+    /// Byte the VTX sends without a known meaning, neither decoded nor
+    /// encoded.
+    Reserved,
+}
+
+/// Position and encoding of one field of a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FieldInfo {
+    pub name: &'static str,
+    /// Offset from the first header byte.
+    pub offset: usize,
+    pub len: usize,
+    pub kind: FieldKind,
+    /// Not needed to decode the frame, e.g. the V2.1 power table or a
+    /// reserved byte.
+    pub optional: bool,
+}
+
+impl FieldInfo {
+    pub(crate) const EMPTY: Self = Self::new("", 0, 0, FieldKind::U8, false);
+
+    pub(crate) const fn new(
+        name: &'static str,
+        offset: usize,
+        len: usize,
+        kind: FieldKind,
+        optional: bool,
+    ) -> Self {
+        Self {
+            name,
+            offset,
+            len,
+            kind,
+            optional,
+        }
+    }
+}
+
+/// Frame declared with `smartaudio_frames!`, see the implementations in
+/// [`crate::commands`] and [`crate::responses`].
+pub trait FrameLayout: Sized {
+    /// Side of the link sending this frame.
+    const ORIGIN: FrameOrigin;
+    /// Fields in wire order.
+    const FIELDS: &'static [FieldInfo];
+
+    /// Command byte on the wire.
+    fn command(&self) -> u8;
+
+    /// Writes the payload to `buffer` and returns its length.
+    fn encode_payload(&self, buffer: &mut [u8]) -> Result<usize, SmartAudioError>;
+
+    /// Decodes the payload of a frame with command byte `command`.
+    fn decode(command: u8, payload: &[u8]) -> Result<Self, SmartAudioError>;
+
+    /// Value with every field set, used by the generated round-trip tests.
+    #[cfg(test)]
+    fn sample() -> Self;
+}
+
+/// Declares frames once and generates the struct, its encoder, its fallible
+/// decoder, the [`FieldInfo`] table and a round-trip test per frame.
+///
+/// Each frame is `Host struct Name(command) { .. }` or `Vtx struct Name(command) { .. }`
+/// with fields in wire order, each with its visibility:
+///
+/// - `name: u8` and `name: u16`, the latter big endian,
+/// - `name: Power` for a power level or dBm byte,
+/// - `flags { name = MASK, .. }` for one byte of `bool` fields,
+/// - `name: Version = command` for a version carried by the command byte,
+/// - `name: Option<Type> if condition => { field: u8, .. }` for trailing
+///   fields present only when `condition` on earlier fields holds,
+/// - `name: Reserved` for a trailing byte that is only listed in the field
+///   table, it has no struct field and is not required on decode.
+///
+/// Host frames also get `const fn` encoders and [`crate::SmartAudioCommand`],
+/// VTX frames get [`crate::responses::SmartAudioReponse`].
+macro_rules! smartaudio_frames {
+    ($(
+        $(#[$attr:meta])*
+        $origin:ident struct $name:ident($command:expr) { $($body:tt)* }
+    )*) => {
+        $(
+            $crate::layout::smartaudio_frames!(@munch
+                {$(#[$attr])* $origin $name ($command)}
+                [payload offset fields i self command]
+                [] [] [] [] [] [] [] [] []
+                $($body)*
+            );
+        )*
+
+        #[cfg(test)]
+        mod round_trip {
+            $(
+                #[test]
+                #[allow(non_snake_case)]
+                fn $name() {
+                    $crate::layout::check_round_trip::<super::$name>();
+                }
+            )*
+        }
+    };
+
+    // Accumulators: struct fields, encoder, decoder, field table, sample,
+    // command override, field names, fixed payload length, field count.
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        $vis:vis $field:ident: u8, $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)* $vis $field: u8,]
+            [$($encode)* $payload[$offset] = $this.$field; $offset += 1;]
+            [$($decode)* let $field = $payload[$offset]; $offset += 1;]
+            [$($info)*
+                $table[$i] = $crate::layout::FieldInfo::new(
+                    stringify!($field), $offset, 1, $crate::layout::FieldKind::U8, false,
+                );
+                $i += 1;
+                $offset += 1;
+            ]
+            [$($sample)* $field: 0x5A,]
+            [$($command)*] [$($names)* $field,] [$($len)* + 1] [$($count)* + 1]
+            $($rest)*
+        );
+    };
+
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        $vis:vis $field:ident: u16, $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)* $vis $field: u16,]
+            [$($encode)*
+                let bytes = $this.$field.to_be_bytes();
+                $payload[$offset] = bytes[0];
+                $payload[$offset + 1] = bytes[1];
+                $offset += 2;
+            ]
+            [$($decode)*
+                let $field = u16::from_be_bytes([$payload[$offset], $payload[$offset + 1]]);
+                $offset += 2;
+            ]
+            [$($info)*
+                $table[$i] = $crate::layout::FieldInfo::new(
+                    stringify!($field), $offset, 2, $crate::layout::FieldKind::U16, false,
+                );
+                $i += 1;
+                $offset += 2;
+            ]
+            [$($sample)* $field: 5865,]
+            [$($command)*] [$($names)* $field,] [$($len)* + 2] [$($count)* + 1]
+            $($rest)*
+        );
+    };
+
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        $vis:vis $field:ident: Power, $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)* $vis $field: $crate::commands::Power,]
+            [$($encode)* $payload[$offset] = $this.$field.to_byte(); $offset += 1;]
+            [$($decode)*
+                let $field = $crate::commands::Power::from_byte($payload[$offset]);
+                $offset += 1;
+            ]
+            [$($info)*
+                $table[$i] = $crate::layout::FieldInfo::new(
+                    stringify!($field), $offset, 1, $crate::layout::FieldKind::Power, false,
+                );
+                $i += 1;
+                $offset += 1;
+            ]
+            [$($sample)* $field: $crate::commands::Power::dBm(14),]
+            [$($command)*] [$($names)* $field,] [$($len)* + 1] [$($count)* + 1]
+            $($rest)*
+        );
+    };
+
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        flags { $($vis:vis $flag:ident = $mask:expr),+ $(,)? }, $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)* $($vis $flag: bool,)+]
+            [$($encode)* $payload[$offset] = 0 $(| ($this.$flag as u8 * $mask))+; $offset += 1;]
+            [$($decode)* $(let $flag = $payload[$offset] & $mask != 0;)+ $offset += 1;]
+            [$($info)*
+                $(
+                    $table[$i] = $crate::layout::FieldInfo::new(
+                        stringify!($flag), $offset, 1, $crate::layout::FieldKind::Flag($mask), false,
+                    );
+                    $i += 1;
+                )+
+                $offset += 1;
+            ]
+            [$($sample)* $($flag: true,)+]
+            [$($command)*] [$($names)* $($flag,)+] [$($len)* + 1]
+            [$($count)* + [$(stringify!($flag)),+].len()]
+            $($rest)*
+        );
+    };
+
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        $vis:vis $field:ident: Version = command, $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)* $vis $field: $crate::responses::Version,]
+            [$($encode)*]
+            [$($decode)* let $field = $crate::responses::Version::from($cmd);]
+            [$($info)*
+                $table[$i] = $crate::layout::FieldInfo::new(
+                    stringify!($field), 2, 1, $crate::layout::FieldKind::Version, false,
+                );
+                $i += 1;
+            ]
+            [$($sample)* $field: $crate::responses::Version::V2_1,]
+            [$($command)* $cmd = $this.$field.settings_command();]
+            [$($names)* $field,] [$($len)*] [$($count)* + 1]
+            $($rest)*
+        );
+    };
+
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        $vis:vis $field:ident: Option<$type:ident> if $condition:expr => { $($sub:ident: u8),+ $(,)? },
+        $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)* $vis $field: Option<$type>,]
+            [$($encode)*
+                if let Some(value) = $this.$field {
+                    $(
+                        $payload[$offset] = value.$sub;
+                        $offset += 1;
+                    )+
+                }
+            ]
+            [$($decode)*
+                let $field = if $condition {
+                    if $payload.len() < $offset + [$(stringify!($sub)),+].len() {
+                        return Err($crate::parser::SmartAudioError::InvalidPayloadLength);
+                    }
+                    Some($type {
+                        $($sub: {
+                            $offset += 1;
+                            $payload[$offset - 1]
+                        },)+
+                    })
+                } else {
+                    None
+                };
+            ]
+            [$($info)*
+                $(
+                    $table[$i] = $crate::layout::FieldInfo::new(
+                        stringify!($sub), $offset, 1, $crate::layout::FieldKind::U8, true,
+                    );
+                    $i += 1;
+                    $offset += 1;
+                )+
+            ]
+            [$($sample)* $field: Some($type { $($sub: 0x5A,)+ }),]
+            [$($command)*] [$($names)* $field,] [$($len)*]
+            [$($count)* + [$(stringify!($sub)),+].len()]
+            $($rest)*
+        );
+    };
+
+    (@munch $head:tt [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+        $field:ident: Reserved, $($rest:tt)*
+    ) => {
+        $crate::layout::smartaudio_frames!(@munch $head [$payload $offset $table $i $this $cmd]
+            [$($fields)*] [$($encode)*] [$($decode)*]
+            [$($info)*
+                $table[$i] = $crate::layout::FieldInfo::new(
+                    stringify!($field), $offset, 1, $crate::layout::FieldKind::Reserved, true,
+                );
+                $i += 1;
+                $offset += 1;
+            ]
+            [$($sample)*] [$($command)*] [$($names)*] [$($len)*] [$($count)* + 1]
+            $($rest)*
+        );
+    };
+
+    (@munch {$(#[$attr:meta])* $origin:ident $name:ident ($default_command:expr)}
+        [$payload:ident $offset:ident $table:ident $i:ident $this:ident $cmd:ident]
+        [$($fields:tt)*] [$($encode:tt)*] [$($decode:tt)*] [$($info:tt)*] [$($sample:tt)*]
+        [$($command:tt)*] [$($names:tt)*] [$($len:tt)*] [$($count:tt)*]
+    ) => {
+        $(#[$attr])*
+        pub struct $name {
+            $($fields)*
+        }
+
+        impl $name {
+            /// Payload length, without fields that depend on the version.
+            pub const PAYLOAD_LEN: usize = 0 $($len)*;
+
+            pub const fn command(&$this) -> u8 {
+                #[allow(unused_mut, unused_assignments)]
+                let mut $cmd = $default_command;
+                $($command)*
+                $cmd
+            }
+
+            /// Writes the payload to the start of `payload` and returns its
+            /// length, panics if it does not fit.
+            pub(crate) const fn write_payload(&$this, $payload: &mut [u8]) -> usize {
+                let _ = &$payload;
+                #[allow(unused_mut)]
+                let mut $offset = 0;
+                $($encode)*
+                $offset
+            }
+        }
+
+        impl $crate::layout::FrameLayout for $name {
+            const ORIGIN: $crate::parser::FrameOrigin = $crate::parser::FrameOrigin::$origin;
+            const FIELDS: &'static [$crate::layout::FieldInfo] = &{
+                #[allow(unused_mut)]
+                let mut $table = [$crate::layout::FieldInfo::EMPTY; 0 $($count)*];
+                #[allow(unused_mut)]
+                let mut $i = 0;
+                #[allow(unused_mut)]
+                let mut $offset = 4;
+                $($info)*
+                let _ = ($i, $offset);
+                $table
+            };
+
+            fn command(&self) -> u8 {
+                $name::command(self)
+            }
+
+            fn encode_payload(
+                &self,
+                buffer: &mut [u8],
+            ) -> Result<usize, $crate::parser::SmartAudioError> {
+                let mut payload = [0; $crate::constants::MAX_PAYLOAD_SIZE];
+                let len = self.write_payload(&mut payload);
+                let Some(target) = buffer.get_mut(..len) else {
+                    return Err($crate::parser::SmartAudioError::BufferTooSmall(buffer.len()));
+                };
+                target.copy_from_slice(&payload[..len]);
+                Ok(len)
+            }
+
+            fn decode(
+                $cmd: u8,
+                $payload: &[u8],
+            ) -> Result<Self, $crate::parser::SmartAudioError> {
+                let _ = $cmd;
+                if $payload.len() < Self::PAYLOAD_LEN {
+                    return Err($crate::parser::SmartAudioError::InvalidPayloadLength);
+                }
+                #[allow(unused_mut)]
+                let mut $offset = 0;
+                $($decode)*
+                let _ = $offset;
+                Ok(Self { $($names)* })
+            }
+
+            #[cfg(test)]
+            fn sample() -> Self {
+                Self { $($sample)* }
+            }
+        }
+
+        $crate::layout::smartaudio_frames!(@origin $origin $name);
+    };
+
+    (@origin Host $name:ident) => {
+        impl $name {
+            pub const fn to_frame(&self) -> $crate::commands::Frame<{ $name::PAYLOAD_LEN + 5 }> {
+                let mut payload = [0; $name::PAYLOAD_LEN];
+                self.write_payload(&mut payload);
+                $crate::commands::Frame::encode(self.command(), &payload)
+            }
+
+            pub const fn to_array(&self) -> [u8; $name::PAYLOAD_LEN + 5] {
+                self.to_frame().into_array()
+            }
+        }
+
+        impl $crate::commands::SmartAudioCommand for $name {
+            fn to_bytes(
+                &self,
+                buffer: &mut [u8],
+            ) -> Result<usize, $crate::parser::SmartAudioError> {
+                self.to_frame().write_to(buffer)
+            }
+        }
+    };
+
+    (@origin Vtx $name:ident) => {
+        impl $crate::responses::SmartAudioReponse for $name {
+            fn from_raw_frame(raw_frame: &$crate::parser::RawSmartAudioFrame<'_>) -> Self {
+                let decoded = <Self as $crate::layout::FrameLayout>::decode(
+                    raw_frame.commnand(),
+                    raw_frame.payload(),
+                );
+                match decoded {
+                    Ok(frame) => frame,
+                    Err(_) => panic!("payload too short for {}", stringify!($name)),
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use smartaudio_frames;
+
+/// Encodes the sample of `F`, parses it back and checks the field table.
+#[cfg(test)]
+pub(crate) fn check_round_trip<F: FrameLayout + PartialEq + core::fmt::Debug>() {
+    use crate::builder::RawFrameBuilder;
+    use crate::constants::MAX_FRAME_SIZE;
+    use crate::constants::MAX_PAYLOAD_SIZE;
+    use crate::parser::ParserConfig;
+    use crate::parser::SmartAudioParser;
+
+    let value = F::sample();
+    let mut payload = [0; MAX_PAYLOAD_SIZE];
+    let len = value.encode_payload(&mut payload).unwrap();
+    let mut buffer = [0; MAX_FRAME_SIZE];
+    let size = RawFrameBuilder::new(value.command(), &payload[..len])
+        .origin(F::ORIGIN)
+        .build(&mut buffer)
+        .unwrap();
+
+    let mut parser = SmartAudioParser::<MAX_FRAME_SIZE>::with_config(ParserConfig::ANY);
+    let frame = parser.push_bytes(&buffer[..size]).1.unwrap().unwrap();
+    assert_eq!(frame.origin(), F::ORIGIN);
+    assert_eq!(F::decode(frame.commnand(), frame.payload()), Ok(value));
+
+    if len > 0 {
+        assert_eq!(
+            F::decode(frame.commnand(), &frame.payload()[..len - 1]),
+            Err(SmartAudioError::InvalidPayloadLength)
+        );
+    }
+    // Reserved bytes are not encoded, the VTX appends them.
+    let encoded = F::FIELDS
+        .iter()
+        .filter(|field| field.kind != FieldKind::Reserved);
+    for field in encoded {
+        assert!(
+            field.offset + field.len < size,
+            "{} out of frame",
+            field.name
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::SetModeCommand;
+    use crate::responses::SetChannelResponse;
+    use crate::responses::Settings;
+
+    #[test]
+    fn test_field_tables() {
+        let fields = Settings::FIELDS;
+        assert_eq!(fields.len(), 15);
+        assert_eq!(
+            fields[0],
+            FieldInfo::new("version", 2, 1, FieldKind::Version, false)
+        );
+        assert_eq!(fields[1].offset, 4);
+        assert_eq!(
+            fields[8],
+            FieldInfo::new("frequency", 7, 2, FieldKind::U16, false)
+        );
+        assert!(fields[9..].iter().all(|field| field.optional));
+        assert_eq!(fields[14].offset, 14);
+
+        let flags = SetModeCommand::FIELDS;
+        assert!(flags.iter().all(|field| field.offset == 4));
+        assert_eq!(flags[3].kind, FieldKind::Flag(0x08));
+        assert_eq!(SetModeCommand::PAYLOAD_LEN, 1);
+
+        let reserved = SetChannelResponse::FIELDS[1];
+        assert_eq!(
+            reserved,
+            FieldInfo::new("reserved", 5, 1, FieldKind::Reserved, true)
+        );
+        assert_eq!(SetChannelResponse::PAYLOAD_LEN, 1);
+    }
+}
@@ -1,12 +1,40 @@
 #![no_std]
 #![allow(clippy::needless_doctest_main)]
 #![doc = include_str!("../README.md")]
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod baud;
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod capture;
 pub mod commands;
 pub(crate) mod constants;
+pub mod crc;
+pub mod dissect;
+pub mod events;
+pub mod health;
+pub mod hex;
+#[cfg(feature = "std")]
+pub mod import;
+pub mod layout;
+#[cfg(feature = "embedded-hal-nb")]
+pub mod nb_driver;
 pub mod parser;
+pub mod presence;
+pub mod queue;
 pub mod responses;
+pub mod sniffer;
+pub mod soft_uart;
+pub mod state;
+pub mod timing;
+#[cfg(feature = "embedded-hal")]
+pub mod transceiver;
+pub mod transcript;
 
 //Command frames
+pub use commands::Command;
+pub use commands::Frame;
 pub use commands::GetSettingsCommand;
 pub use commands::SetChannelCommand;
 pub use commands::SetFrequencyCommand;
@@ -23,6 +51,45 @@ pub use responses::SetPowerResponse;
 pub use responses::Settings;
 pub use responses::SmartAudioReponse;
 // Parsing
+pub use builder::RawFrameBuilder;
+pub use crc::Crc8DvbS2;
+pub use dissect::dissect;
+pub use dissect::Field;
+pub use dissect::FieldValue;
+pub use hex::decode_hex;
+pub use hex::Decoded;
+pub use parser::iter_frames;
+pub use parser::FrameOrigin;
+pub use parser::OwnedFrame;
+pub use parser::ParserConfig;
 pub use parser::RawSmartAudioFrame;
 pub use parser::SmartAudioError;
 pub use parser::SmartAudioParser;
+pub use queue::FrameQueue;
+pub use sniffer::Exchange;
+pub use sniffer::Sniffer;
+pub use transcript::channel_name;
+pub use transcript::Transcript;
+
+// Host side state tracking
+pub use baud::BaudSearch;
+pub use baud::BaudUpdate;
+pub use baud::DetectedVtx;
+pub use events::ChangeDetector;
+pub use events::VtxEvent;
+pub use health::LinkHealth;
+pub use health::LinkQuality;
+pub use presence::PresenceEvent;
+pub use presence::PresenceSupervisor;
+pub use state::Observed;
+pub use state::VtxState;
+
+// Transport helpers
+#[cfg(feature = "std")]
+pub use capture::Capture;
+#[cfg(feature = "embedded-hal-nb")]
+pub use nb_driver::NbDriver;
+pub use soft_uart::SoftUartRx;
+pub use soft_uart::Waveform;
+#[cfg(feature = "embedded-hal")]
+pub use transceiver::DirectionControl;
@@ -0,0 +1,222 @@
+use embedded_hal_nb::nb;
+use embedded_hal_nb::serial::Read;
+use embedded_hal_nb::serial::Write;
+
+use crate::commands::SmartAudioCommand;
+use crate::constants::MAX_FRAME_SIZE;
+use crate::parser::SmartAudioError;
+use crate::parser::SmartAudioParser;
+use crate::responses::Response;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DriverError<E> {
+    Serial(E),
+    /// The command could not be encoded.
+    Encode(SmartAudioError),
+    /// The reply was corrupted, the transaction is aborted.
+    Parser(SmartAudioError),
+    /// `poll` was called without a transaction in flight.
+    Idle,
+    /// `start` was called while a transaction is in flight.
+    Busy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Phase {
+    Idle,
+    Sending,
+    Flushing,
+    Receiving,
+}
+
+/// Poll based `SmartAudio` driver over a non-blocking serial port.
+///
+/// [`Self::start`] queues a command, then each [`Self::poll`] writes and
+/// reads as many bytes as the port accepts without blocking and returns
+/// [`nb::Error::WouldBlock`] until a response is parsed. Garbage and the
+/// echo of our own command on single-wire setups are skipped. The driver has
+/// no clock, use [`Self::cancel`] to give up on a VTX that does not answer.
+#[derive(Debug)]
+pub struct NbDriver<S> {
+    serial: S,
+    parser: SmartAudioParser,
+    tx: [u8; MAX_FRAME_SIZE],
+    tx_len: usize,
+    tx_position: usize,
+    phase: Phase,
+}
+
+impl<S, E> NbDriver<S>
+where
+    S: Read<u8, Error = E> + Write<u8, Error = E>,
+{
+    pub fn new(serial: S) -> Self {
+        Self {
+            serial,
+            parser: SmartAudioParser::new(),
+            tx: [0; MAX_FRAME_SIZE],
+            tx_len: 0,
+            tx_position: 0,
+            phase: Phase::Idle,
+        }
+    }
+
+    pub fn free(self) -> S {
+        self.serial
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.phase != Phase::Idle
+    }
+
+    /// Queues `command` as the next transaction.
+    pub fn start<C: SmartAudioCommand>(&mut self, command: &C) -> Result<(), DriverError<E>> {
+        if self.is_busy() {
+            return Err(DriverError::Busy);
+        }
+        self.tx_len = command
+            .to_bytes(&mut self.tx)
+            .map_err(DriverError::Encode)?;
+        self.tx_position = 0;
+        self.parser.reset();
+        self.phase = Phase::Sending;
+        Ok(())
+    }
+
+    /// Drops the current transaction, e.g. after a timeout.
+    pub fn cancel(&mut self) {
+        self.parser.reset();
+        self.phase = Phase::Idle;
+    }
+
+    /// Advances the current transaction without blocking.
+    pub fn poll(&mut self) -> nb::Result<Response, DriverError<E>> {
+        if self.phase == Phase::Idle {
+            return Err(nb::Error::Other(DriverError::Idle));
+        }
+        while self.phase == Phase::Sending {
+            let byte = self.tx[self.tx_position];
+            self.serial.write(byte).map_err(serial_error)?;
+            self.tx_position += 1;
+            if self.tx_position == self.tx_len {
+                self.phase = Phase::Flushing;
+            }
+        }
+        if self.phase == Phase::Flushing {
+            self.serial.flush().map_err(serial_error)?;
+            self.phase = Phase::Receiving;
+        }
+        loop {
+            let byte = self.serial.read().map_err(serial_error)?;
+            match self.parser.push_byte(byte) {
+                Ok(Some(response)) => {
+                    self.phase = Phase::Idle;
+                    return Ok(response);
+                }
+                Ok(None) | Err(SmartAudioError::UnexpetedDataForState(..)) => (),
+                Err(e) => {
+                    self.cancel();
+                    return Err(nb::Error::Other(DriverError::Parser(e)));
+                }
+            }
+        }
+    }
+}
+
+fn serial_error<E>(error: nb::Error<E>) -> nb::Error<DriverError<E>> {
+    error.map(DriverError::Serial)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::commands::GetSettingsCommand;
+    use core::convert::Infallible;
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    /// Single-wire port echoing what is written and accepting two bytes per poll.
+    #[derive(Default)]
+    struct Port {
+        rx: VecDeque<u8>,
+        written: Vec<u8>,
+        budget: usize,
+    }
+
+    impl embedded_hal_nb::serial::ErrorType for Port {
+        type Error = Infallible;
+    }
+
+    impl Read<u8> for Port {
+        fn read(&mut self) -> nb::Result<u8, Self::Error> {
+            self.rx.pop_front().ok_or(nb::Error::WouldBlock)
+        }
+    }
+
+    impl Write<u8> for Port {
+        fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+            if self.budget == 0 {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.budget -= 1;
+            self.written.push(word);
+            self.rx.push_back(word);
+            Ok(())
+        }
+
+        fn flush(&mut self) -> nb::Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    const SETTINGS: [u8; 10] = [0xAA, 0x55, 0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9, 0x0A];
+
+    #[test]
+    fn test_transaction() {
+        let mut driver = NbDriver::new(Port::default());
+        assert!(matches!(
+            driver.poll(),
+            Err(nb::Error::Other(DriverError::Idle))
+        ));
+        driver.start(&GetSettingsCommand {}).unwrap();
+        assert!(matches!(
+            driver.start(&GetSettingsCommand {}),
+            Err(DriverError::Busy)
+        ));
+
+        for _ in 0..3 {
+            driver.serial.budget = 2;
+            assert!(matches!(driver.poll(), Err(nb::Error::WouldBlock)));
+        }
+        assert_eq!(driver.serial.written, [0xAA, 0x55, 0x03, 0x00, 0x9F]);
+
+        driver.serial.rx.extend(&SETTINGS[..4]);
+        assert!(matches!(driver.poll(), Err(nb::Error::WouldBlock)));
+        driver.serial.rx.extend(&SETTINGS[4..]);
+        let response = driver.poll().unwrap();
+        assert!(matches!(response, Response::GetSettings(s) if s.channel == 1));
+        assert!(!driver.is_busy());
+    }
+
+    #[test]
+    fn test_corrupted_reply_aborts() {
+        let mut driver = NbDriver::new(Port {
+            budget: 5,
+            ..Default::default()
+        });
+        driver.start(&GetSettingsCommand {}).unwrap();
+        let mut corrupted = SETTINGS;
+        corrupted[9] ^= 0xFF;
+        driver.serial.rx.extend(&corrupted);
+        assert!(matches!(
+            driver.poll(),
+            Err(nb::Error::Other(DriverError::Parser(
+                SmartAudioError::InvalidCrc { .. }
+            )))
+        ));
+        assert!(!driver.is_busy());
+    }
+}
@@ -1,19 +1,22 @@
+use core::ops::Range;
+
+use crate::commands::GetSettingsCommand;
+use crate::commands::SetChannelCommand;
+use crate::commands::SetFrequencyCommand;
+use crate::commands::SetModeCommand;
+use crate::commands::SetPowerCommand;
 use crate::constants;
+use crate::constants::command;
+use crate::constants::response;
+use crate::crc::Crc8DvbS2;
+use crate::responses::SetChannelResponse;
+use crate::responses::SetFrequencyResponse;
+use crate::responses::SetModeResponse;
+use crate::responses::SetPowerResponse;
+use crate::responses::Settings;
 
-fn crc8_dvb_s2(data: &[u8]) -> u8 {
-    let mut crc = 0;
-    for byte in data {
-        crc ^= byte;
-        for _ in 0..8 {
-            if crc & 0x80 != 0 {
-                crc = (crc << 1) ^ 0xD5;
-            } else {
-                crc <<= 1;
-            }
-        }
-    }
-    crc
-}
+/// CRC of the two header bytes, the starting point of host frame CRCs.
+const HEADER_CRC: u8 = Crc8DvbS2::checksum(&[constants::HEADER_BYTE_1, constants::HEADER_BYTE_2]);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -41,7 +44,7 @@ pub fn frame_payload(
     buffer[3] = payload_size as u8;
     buffer[4..4 + payload_size].copy_from_slice(payload);
 
-    let crc = crc8_dvb_s2(&buffer[0..payload_size + 4]);
+    let crc = Crc8DvbS2::checksum(&buffer[0..payload_size + 4]);
     buffer[payload_size + 4] = crc;
     Ok(payload_size + 5)
 }
@@ -57,16 +60,36 @@ pub enum State {
     Reading(usize),
 }
 
+/// Which side of the link sent a frame.
+///
+/// Known commands are told apart by the command byte and length: host
+/// commands are `(command << 1) | 1` with a fixed payload, VTX replies use
+/// the plain command number or a version byte and count the CRC in the
+/// length. Other frames fall back to the CRC style: host commands include
+/// the header in the CRC, VTX responses do not.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameOrigin {
+    Host,
+    #[default]
+    Vtx,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct RawSmartAudioFrame<'a> {
     bytes: &'a [u8],
+    origin: FrameOrigin,
 }
 
 impl<'a> RawSmartAudioFrame<'a> {
     pub(crate) fn new(bytes: &'a [u8]) -> Option<Self> {
+        Self::with_origin(bytes, FrameOrigin::Vtx)
+    }
+
+    pub(crate) fn with_origin(bytes: &'a [u8], origin: FrameOrigin) -> Option<Self> {
         if bytes.len() >= 4 {
-            Some(Self { bytes })
+            Some(Self { bytes, origin })
         } else {
             None
         }
@@ -76,6 +99,11 @@ impl<'a> RawSmartAudioFrame<'a> {
         self.bytes[2]
     }
 
+    /// Whole frame, header and CRC included.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
     pub fn payload(&self) -> &[u8] {
         &self.bytes[4..self.bytes.len() - 1]
     }
@@ -88,6 +116,10 @@ impl<'a> RawSmartAudioFrame<'a> {
         self.bytes.is_empty()
     }
 
+    pub fn origin(&self) -> FrameOrigin {
+        self.origin
+    }
+
     /// Returns the CRC check byte of the frame.
     #[expect(clippy::missing_panics_doc, reason = "infallible")]
     pub fn crc(&self) -> u8 {
@@ -95,25 +127,192 @@ impl<'a> RawSmartAudioFrame<'a> {
     }
 }
 
+/// Frame copied out of the parser buffer, so it can be kept while more bytes
+/// are pushed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct OwnedFrame<const N: usize = { constants::MAX_FRAME_SIZE }> {
+    bytes: [u8; N],
+    len: usize,
+    origin: FrameOrigin,
+}
+
+impl<const N: usize> OwnedFrame<N> {
+    pub fn as_raw(&self) -> RawSmartAudioFrame<'_> {
+        RawSmartAudioFrame {
+            bytes: self.as_bytes(),
+            origin: self.origin,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+
+    pub fn commnand(&self) -> u8 {
+        self.bytes[2]
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.bytes[4..self.len - 1]
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn origin(&self) -> FrameOrigin {
+        self.origin
+    }
+
+    pub fn crc(&self) -> u8 {
+        self.bytes[self.len - 1]
+    }
+}
+
+/// Fails with [`SmartAudioError::InvalidPayloadLength`] if the frame is
+/// longer than `N`, which cannot happen for frames produced by a
+/// `SmartAudioParser<N>`.
+impl<const N: usize> TryFrom<&RawSmartAudioFrame<'_>> for OwnedFrame<N> {
+    type Error = SmartAudioError;
+
+    fn try_from(raw_frame: &RawSmartAudioFrame<'_>) -> Result<Self, Self::Error> {
+        let mut bytes = [0; N];
+        bytes
+            .get_mut(..raw_frame.len())
+            .ok_or(SmartAudioError::InvalidPayloadLength)?
+            .copy_from_slice(raw_frame.bytes);
+        Ok(Self {
+            bytes,
+            len: raw_frame.len(),
+            origin: raw_frame.origin,
+        })
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for OwnedFrame<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("OwnedFrame")
+            .field("bytes", &self.as_bytes())
+            .field("origin", &self.origin)
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for OwnedFrame<N> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "OwnedFrame {{ bytes: {=[u8]}, origin: {} }}",
+            self.as_bytes(),
+            self.origin
+        );
+    }
+}
+
+/// Limits applied by [`SmartAudioParser`] to incoming frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ParserConfig {
+    /// Smallest accepted value of the length byte.
+    pub min_length: u8,
+    /// Largest accepted value of the length byte, further capped by the
+    /// parser buffer size.
+    pub max_length: u8,
+    /// Accept frames sent by the host, with the header included in the CRC.
+    pub accept_host_frames: bool,
+    /// Accept frames sent by the VTX, with the header excluded from the CRC.
+    pub accept_vtx_frames: bool,
+}
+
+impl ParserConfig {
+    /// VTX responses of the lengths defined by the specification.
+    pub const VTX: Self = Self {
+        min_length: constants::MIN_PAYLOAD_SIZE as u8,
+        max_length: constants::MAX_PAYLOAD_SIZE as u8 - 1,
+        accept_host_frames: false,
+        accept_vtx_frames: true,
+    };
+
+    /// Frames from both sides of the link with any length that fits the buffer.
+    pub const ANY: Self = Self {
+        min_length: 0,
+        max_length: u8::MAX,
+        accept_host_frames: true,
+        accept_vtx_frames: true,
+    };
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self::VTX
+    }
+}
+
+/// Streaming frame parser with an `N` byte frame buffer.
+///
+/// Host frames are `length + 5` bytes long and VTX frames `length + 4`,
+/// as the VTX counts the CRC in the length byte. Lengths that would not fit
+/// into `N` bytes are rejected whatever the [`ParserConfig`] says.
 #[derive(Debug)]
-pub struct SmartAudioParser {
-    buffer: [u8; constants::MAX_FRAME_SIZE],
+pub struct SmartAudioParser<const N: usize = { constants::MAX_FRAME_SIZE }> {
+    buffer: [u8; N],
     state: State,
     position: usize,
+    config: ParserConfig,
+    vtx_crc: Crc8DvbS2,
+    host_crc: Crc8DvbS2,
+    /// Whether the frame being read may be a host frame.
+    host_frame: bool,
+    /// Whether the frame being read may be a VTX frame.
+    vtx_frame: bool,
 }
 
 impl SmartAudioParser {
     pub fn new() -> Self {
+        Self::with_config(ParserConfig::default())
+    }
+}
+
+impl<const N: usize> SmartAudioParser<N> {
+    /// Panics at compile time if `N` is below 5 bytes, the size of the
+    /// shortest host frame.
+    pub fn with_config(config: ParserConfig) -> Self {
+        const { assert!(N >= 5, "parser buffer must hold at least 5 bytes") };
         Self {
-            buffer: [0; constants::MAX_FRAME_SIZE],
+            buffer: [0; N],
             state: State::AwaitingHeader1,
             position: 0,
+            config,
+            vtx_crc: Crc8DvbS2::new(),
+            host_crc: Crc8DvbS2::from_value(HEADER_CRC),
+            host_frame: false,
+            vtx_frame: false,
         }
     }
 
+    pub fn config(&self) -> &ParserConfig {
+        &self.config
+    }
+
     pub fn reset(&mut self) {
         self.position = 0;
         self.state = State::AwaitingHeader1;
+        self.vtx_crc = Crc8DvbS2::new();
+        self.host_crc = Crc8DvbS2::from_value(HEADER_CRC);
+    }
+
+    fn update_crc(&mut self, byte: u8) {
+        self.vtx_crc.update_byte(byte);
+        self.host_crc.update_byte(byte);
+    }
+
+    pub(crate) fn is_idle(&self) -> bool {
+        self.state == State::AwaitingHeader1
     }
 
     pub fn push_byte_raw(
@@ -136,40 +335,73 @@ impl SmartAudioParser {
             State::AwaitingCommand => {
                 self.position += 1;
                 self.buffer[self.position] = byte;
+                self.update_crc(byte);
                 self.state = State::AwaitingLength;
                 Ok(None)
             }
-            State::AwaitingLength
-                if (constants::MIN_PAYLOAD_SIZE..constants::MAX_PAYLOAD_SIZE)
-                    .contains(&(byte as usize)) =>
-            {
+            State::AwaitingLength => {
+                (self.host_frame, self.vtx_frame) =
+                    frame_origins::<N>(&self.config, self.buffer[2], byte as usize);
+                if !self.host_frame && !self.vtx_frame {
+                    self.reset();
+                    return Err(SmartAudioError::UnexpetedDataForState(
+                        State::AwaitingLength,
+                        byte,
+                    ));
+                }
                 self.position += 1;
                 self.buffer[self.position] = byte;
+                self.update_crc(byte);
                 self.state = State::Reading(byte as usize);
                 Ok(None)
             }
             State::Reading(n) => {
                 self.position += 1;
                 self.buffer[self.position] = byte;
-                if self.position == n + 3 {
-                    let start = 0;
-                    let end = self.position + 1;
-
-                    let calculated_crc = crc8_dvb_s2(&self.buffer[2..end - 1]);
-
-                    let frame_crc = self.buffer[self.position];
-                    if frame_crc != calculated_crc {
-                        return Err(SmartAudioError::InvalidCrc {
-                            frame_crc,
-                            calculated_crc,
-                        });
+                let vtx_end = self.vtx_frame && self.position == n + 3;
+                let host_end = self.host_frame && self.position == n + 4;
+                if !vtx_end && !host_end {
+                    self.update_crc(byte);
+                    return Ok(None);
+                }
+
+                let end = self.position + 1;
+                let frame_crc = byte;
+                let (origin, calculated_crc) = if vtx_end {
+                    (FrameOrigin::Vtx, self.vtx_crc.value())
+                } else {
+                    (FrameOrigin::Host, self.host_crc.value())
+                };
+                if frame_crc != calculated_crc {
+                    // A host frame of the same length is one byte longer.
+                    let host_may_follow = vtx_end && self.host_frame;
+                    if host_may_follow {
+                        self.update_crc(byte);
+                        return Ok(None);
+                    }
+                    let vtx_failed = host_end && self.vtx_frame;
+                    if vtx_failed {
+                        // Neither side's CRC matched, report the VTX one and
+                        // keep the extra byte, it may start the next frame.
+                        let error = SmartAudioError::InvalidCrc {
+                            frame_crc: self.buffer[n + 3],
+                            calculated_crc: Crc8DvbS2::checksum(&self.buffer[2..n + 3]),
+                        };
+                        self.reset();
+                        if byte == constants::HEADER_BYTE_1 {
+                            self.buffer[0] = byte;
+                            self.state = State::AwaitingHeader2;
+                        }
+                        return Err(error);
                     }
                     self.reset();
-                    let bytes = &self.buffer[start..end];
-                    Ok(RawSmartAudioFrame::new(bytes))
-                } else {
-                    Ok(None)
+                    return Err(SmartAudioError::InvalidCrc {
+                        frame_crc,
+                        calculated_crc,
+                    });
                 }
+                self.reset();
+                Ok(RawSmartAudioFrame::with_origin(&self.buffer[..end], origin))
             }
             _ => {
                 let current_state = self.state;
@@ -178,6 +410,272 @@ impl SmartAudioParser {
             }
         }
     }
+
+    /// Pushes bytes until a frame completes or an error occurs. Returns how
+    /// many bytes were consumed, the rest should be pushed in the next call.
+    pub fn push_bytes(
+        &mut self,
+        bytes: &[u8],
+    ) -> (usize, Option<Result<OwnedFrame<N>, SmartAudioError>>) {
+        for (i, byte) in bytes.iter().enumerate() {
+            match self.push_byte_raw(*byte) {
+                Ok(None) => (),
+                Ok(Some(raw_frame)) => return (i + 1, Some(OwnedFrame::try_from(&raw_frame))),
+                Err(e) => return (i + 1, Some(Err(e))),
+            }
+        }
+        (bytes.len(), None)
+    }
+
+    /// Same as [`Self::push_bytes`] for data split in two slices, such as the
+    /// tail and head of a circular DMA buffer. Frames straddling the two
+    /// slices are reassembled in the parser buffer.
+    pub fn push_split(
+        &mut self,
+        first: &[u8],
+        second: &[u8],
+    ) -> (usize, Option<Result<OwnedFrame<N>, SmartAudioError>>) {
+        let (consumed, result) = self.push_bytes(first);
+        if result.is_some() {
+            return (consumed, result);
+        }
+        let (consumed_second, result) = self.push_bytes(second);
+        (consumed + consumed_second, result)
+    }
+
+    /// Parses the unread part of a ring buffer, from the consumer index
+    /// `read` up to the producer index `write`. Returns the new consumer
+    /// index: every byte before it was copied into the parser and its slot
+    /// may be reused by the producer.
+    pub fn push_ring(
+        &mut self,
+        ring: &[u8],
+        read: usize,
+        write: usize,
+    ) -> (usize, Option<Result<OwnedFrame<N>, SmartAudioError>>) {
+        if ring.is_empty() {
+            return (0, None);
+        }
+        let (read, write) = (read % ring.len(), write % ring.len());
+        let (first, second) = if write >= read {
+            (&ring[read..write], &ring[..0])
+        } else {
+            (&ring[read..], &ring[..write])
+        };
+        let (consumed, result) = self.push_split(first, second);
+        ((read + consumed) % ring.len(), result)
+    }
+}
+
+/// Iterates over frames in `buffer` without a parser, reporting where each
+/// frame, error and run of garbage bytes is located.
+///
+/// Garbage between frames is yielded as [`SmartAudioError::InvalidHeader`],
+/// a frame cut off by the end of the buffer as
+/// [`SmartAudioError::BufferTooSmall`] with the number of bytes available.
+pub fn iter_frames(buffer: &[u8]) -> FrameIterator<'_> {
+    FrameIterator {
+        buffer,
+        position: 0,
+    }
+}
+
+/// Whether a frame with command byte `cmd` and `length` may be a host frame and a
+/// VTX frame for a `SmartAudioParser<N>` using `config`.
+fn frame_origins<const N: usize>(config: &ParserConfig, cmd: u8, length: usize) -> (bool, bool) {
+    if !(usize::from(config.min_length)..=usize::from(config.max_length)).contains(&length) {
+        return (false, false);
+    }
+    let host = config.accept_host_frames && length + 5 <= N;
+    let vtx = config.accept_vtx_frames && length >= 1 && length + 4 <= N;
+    match known_origin(cmd, length) {
+        Some(FrameOrigin::Host) if host => (true, false),
+        Some(FrameOrigin::Vtx) if vtx => (false, true),
+        _ => (host, vtx),
+    }
+}
+
+/// Side sending frames with command byte `cmd` and `length`, `None` for unknown
+/// commands and lengths. The same byte is used by both sides for some
+/// commands, e.g. 0x03 is `GetSettings` from the host and the `SetChannel`
+/// reply from the VTX, but never with the same length.
+fn known_origin(cmd: u8, length: usize) -> Option<FrameOrigin> {
+    let host_payload = match cmd {
+        command::GET_SETTINGS => Some(GetSettingsCommand::PAYLOAD_LEN),
+        command::SET_POWER => Some(SetPowerCommand::PAYLOAD_LEN),
+        command::SET_CHANNEL => Some(SetChannelCommand::PAYLOAD_LEN),
+        command::SET_FREQUENCY => Some(SetFrequencyCommand::PAYLOAD_LEN),
+        command::SET_MODE => Some(SetModeCommand::PAYLOAD_LEN),
+        _ => None,
+    };
+    let vtx_payload = match cmd {
+        response::GET_SETTINGS_V1_0 | response::GET_SETTINGS_V2_0 | response::GET_SETTINGS_V2_1 => {
+            Some(Settings::PAYLOAD_LEN)
+        }
+        response::SET_POWER => Some(SetPowerResponse::PAYLOAD_LEN),
+        response::SET_CHANNEL => Some(SetChannelResponse::PAYLOAD_LEN),
+        response::SET_FREQUENCY => Some(SetFrequencyResponse::PAYLOAD_LEN),
+        response::SET_MODE => Some(SetModeResponse::PAYLOAD_LEN),
+        _ => None,
+    };
+    // Host lengths count the payload, VTX lengths the payload and the CRC.
+    let host = host_payload == Some(length);
+    let vtx = vtx_payload.is_some_and(|payload| length > payload);
+    match (host, vtx) {
+        (true, false) => Some(FrameOrigin::Host),
+        (false, true) => Some(FrameOrigin::Vtx),
+        _ => None,
+    }
+}
+
+/// Outcome of validating a frame in place, see [`scan_frame`].
+pub(crate) enum Scan<'a> {
+    Frame(RawSmartAudioFrame<'a>),
+    /// The first `len` bytes do not form a valid frame.
+    Error {
+        len: usize,
+        error: SmartAudioError,
+    },
+    /// More bytes are needed to decide.
+    Incomplete,
+}
+
+/// Validates length and CRC of the frame at the start of `bytes` without
+/// copying it, with the same rules as a `SmartAudioParser<N>` using `config`.
+/// `bytes` must start with the two header bytes.
+pub(crate) fn scan_frame<'a, const N: usize>(config: &ParserConfig, bytes: &'a [u8]) -> Scan<'a> {
+    let Some(&length) = bytes.get(3) else {
+        return Scan::Incomplete;
+    };
+    let n = length as usize;
+    let (host_possible, vtx_possible) = frame_origins::<N>(config, bytes[2], n);
+    if !host_possible && !vtx_possible {
+        return Scan::Error {
+            len: 4,
+            error: SmartAudioError::UnexpetedDataForState(State::AwaitingLength, length),
+        };
+    }
+    let mut vtx_error = None;
+    if vtx_possible {
+        let Some(frame) = bytes.get(..n + 4) else {
+            return Scan::Incomplete;
+        };
+        let calculated_crc = Crc8DvbS2::checksum(&frame[2..n + 3]);
+        let frame_crc = frame[n + 3];
+        if calculated_crc == frame_crc {
+            return RawSmartAudioFrame::new(frame).map_or(Scan::Incomplete, Scan::Frame);
+        }
+        let error = SmartAudioError::InvalidCrc {
+            calculated_crc,
+            frame_crc,
+        };
+        if !host_possible {
+            return Scan::Error { len: n + 4, error };
+        }
+        vtx_error = Some(error);
+    }
+    let Some(frame) = bytes.get(..n + 5) else {
+        return Scan::Incomplete;
+    };
+    let mut crc = Crc8DvbS2::from_value(HEADER_CRC);
+    crc.update(&frame[2..n + 4]);
+    let (calculated_crc, frame_crc) = (crc.value(), frame[n + 4]);
+    if calculated_crc == frame_crc {
+        return RawSmartAudioFrame::with_origin(frame, FrameOrigin::Host)
+            .map_or(Scan::Incomplete, Scan::Frame);
+    }
+    // Same as the parser: the byte after a failed VTX frame is not consumed.
+    match vtx_error {
+        Some(error) => Scan::Error { len: n + 4, error },
+        None => Scan::Error {
+            len: n + 5,
+            error: SmartAudioError::InvalidCrc {
+                calculated_crc,
+                frame_crc,
+            },
+        },
+    }
+}
+
+const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+const HIGHS: u64 = u64::from_ne_bytes([0x80; 8]);
+
+/// Position of the first `0xAA 0x55` pair, scanning eight bytes at a time
+/// for the first header byte.
+pub(crate) fn find_header(buffer: &[u8]) -> Option<usize> {
+    let is_header = |i: usize| {
+        buffer[i] == constants::HEADER_BYTE_1
+            && buffer.get(i + 1) == Some(&constants::HEADER_BYTE_2)
+    };
+    let pattern = ONES * u64::from(constants::HEADER_BYTE_1);
+    let mut chunks = buffer.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in chunks.by_ref() {
+        let word = u64::from_le_bytes(chunk.try_into().expect("chunk of 8 bytes")) ^ pattern;
+        // Flags bytes of `word` that are zero, i.e. equal to the first header byte.
+        let found = word.wrapping_sub(ONES) & !word & HIGHS;
+        if found != 0 {
+            let first = found.trailing_zeros() as usize / 8;
+            if let Some(i) = (offset + first..offset + 8).find(|i| is_header(*i)) {
+                return Some(i);
+            }
+        }
+        offset += 8;
+    }
+    (offset..offset + chunks.remainder().len()).find(|i| is_header(*i))
+}
+
+pub struct FrameIterator<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for FrameIterator<'a> {
+    type Item = (
+        Range<usize>,
+        Result<RawSmartAudioFrame<'a>, SmartAudioError>,
+    );
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.position;
+        let rest = self.buffer.get(start..).filter(|rest| !rest.is_empty())?;
+
+        match find_header(rest) {
+            Some(0) => (),
+            Some(offset) => {
+                self.position = start + offset;
+                return Some((start..self.position, Err(SmartAudioError::InvalidHeader)));
+            }
+            None if rest.len() > 1 && rest.last() == Some(&constants::HEADER_BYTE_1) => {
+                // Possibly the first header byte of a truncated frame.
+                self.position = self.buffer.len() - 1;
+                return Some((start..self.position, Err(SmartAudioError::InvalidHeader)));
+            }
+            None if rest != [constants::HEADER_BYTE_1] => {
+                self.position = self.buffer.len();
+                return Some((start..self.position, Err(SmartAudioError::InvalidHeader)));
+            }
+            None => (),
+        }
+
+        match scan_frame::<{ constants::MAX_FRAME_SIZE }>(&ParserConfig::VTX, rest) {
+            Scan::Frame(frame) => {
+                self.position = start + frame.len();
+                Some((start..self.position, Ok(frame)))
+            }
+            Scan::Error { len, error } => {
+                self.position = start + len;
+                Some((start..self.position, Err(error)))
+            }
+            Scan::Incomplete => {
+                self.position = self.buffer.len();
+                Some((
+                    start..self.position,
+                    Err(SmartAudioError::BufferTooSmall(rest.len())),
+                ))
+            }
+        }
+    }
 }
 
 impl Default for SmartAudioParser {
@@ -188,8 +686,9 @@ impl Default for SmartAudioParser {
 
 #[cfg(test)]
 mod tests {
-
+    extern crate std;
     use super::*;
+    use std::vec::Vec;
     #[test]
     fn test_raw_responses_parsing() {
         // Raw data obteainted form protcol specificaton examples
@@ -302,4 +801,276 @@ mod tests {
             assert_eq!(p.crc(), *raw_bytes.last().unwrap());
         }
     }
+
+    #[test]
+    fn test_push_bytes_owned_frames() {
+        let raw: [u8; 20] = [
+            0x00, 0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A, // garbage and frame
+            0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4F, // frame
+            0xAA, 0x55, 0x02, 0x03, 0x00, // partial frame
+        ];
+        let mut parser = SmartAudioParser::new();
+        let mut frames = [None; 2];
+        let mut input = &raw[..];
+
+        let (consumed, result) = parser.push_bytes(input);
+        assert_eq!(consumed, 1);
+        assert!(matches!(
+            result,
+            Some(Err(SmartAudioError::UnexpetedDataForState(..)))
+        ));
+        input = &input[consumed..];
+
+        for frame in frames.iter_mut() {
+            let (consumed, result) = parser.push_bytes(input);
+            *frame = result.map(Result::unwrap);
+            input = &input[consumed..];
+        }
+        assert_eq!(parser.push_bytes(input), (5, None));
+
+        let first = frames[0].unwrap();
+        let second = frames[1].unwrap();
+        assert_eq!(first.as_bytes(), &raw[1..8]);
+        assert_eq!(first.commnand(), 0x03);
+        assert_eq!(first.payload(), [0x00, 0x01]);
+        assert_eq!(second.as_raw().crc(), 0x4F);
+        assert_eq!(second.len(), 7);
+
+        let (consumed, result) = parser.push_bytes(&[0x01, 0x0F]);
+        assert_eq!(consumed, 2);
+        assert_eq!(result.unwrap().unwrap().crc(), 0x0F);
+    }
+
+    #[test]
+    fn test_owned_frame_too_long() {
+        let bytes = [0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4F];
+        let raw_frame = RawSmartAudioFrame::new(&bytes).unwrap();
+        assert_eq!(
+            OwnedFrame::<6>::try_from(&raw_frame).unwrap_err(),
+            SmartAudioError::InvalidPayloadLength
+        );
+        let frame = OwnedFrame::<7>::try_from(&raw_frame).unwrap();
+        assert_eq!(frame.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_push_ring_wraparound() {
+        let frame = [0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4F];
+        let mut ring = [0u8; 8];
+        // Frame written starting at index 5, wrapping after 3 bytes.
+        for (i, byte) in frame.iter().enumerate() {
+            ring[(5 + i) % ring.len()] = *byte;
+        }
+        let mut parser = SmartAudioParser::new();
+
+        // Only the tail is available yet.
+        assert_eq!(parser.push_ring(&ring, 5, 0), (0, None));
+        let (read, result) = parser.push_ring(&ring, 0, 4);
+        assert_eq!(read, 4);
+        assert_eq!(result.unwrap().unwrap().as_bytes(), frame);
+
+        let mut parser = SmartAudioParser::new();
+        let (read, result) = parser.push_ring(&ring, 5, 4);
+        assert_eq!(read, 4);
+        assert_eq!(result.unwrap().unwrap().as_bytes(), frame);
+        assert_eq!(parser.push_ring(&ring, 4, 4), (4, None));
+    }
+
+    #[test]
+    fn test_push_split_stops_after_frame() {
+        let mut parser = SmartAudioParser::new();
+        let first = [0xAA, 0x55, 0x03, 0x03];
+        let second = [0x00, 0x01, 0x4A, 0xAA, 0x55];
+        let (consumed, result) = parser.push_split(&first, &second);
+        assert_eq!(consumed, 7);
+        assert_eq!(result.unwrap().unwrap().payload(), [0x00, 0x01]);
+        assert_eq!(parser.push_split(&second[3..], &[]), (2, None));
+    }
+
+    #[test]
+    fn test_iter_frames_spans() {
+        let raw: [u8; 29] = [
+            0x00, 0x13, // garbage
+            0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A, // frame
+            0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4E, // bad crc
+            0xAA, 0x55, 0x05, 0x40, // bad length
+            0x77, // garbage
+            0xAA, 0x55, 0x02, 0x03, 0x00, 0x01, 0x0F, // frame
+            0xAA, // truncated
+        ];
+        let spans: Vec<_> = iter_frames(&raw).collect();
+        assert_eq!(spans.len(), 7);
+
+        assert_eq!(spans[0].0, 0..2);
+        assert!(matches!(spans[0].1, Err(SmartAudioError::InvalidHeader)));
+
+        assert_eq!(spans[1].0, 2..9);
+        let frame = spans[1].1.as_ref().unwrap();
+        assert_eq!(frame.commnand(), 0x03);
+
+        assert_eq!(spans[2].0, 9..16);
+        assert!(matches!(
+            spans[2].1,
+            Err(SmartAudioError::InvalidCrc {
+                calculated_crc: 0x4F,
+                frame_crc: 0x4E
+            })
+        ));
+
+        assert_eq!(spans[3].0, 16..20);
+        assert!(matches!(
+            spans[3].1,
+            Err(SmartAudioError::UnexpetedDataForState(
+                State::AwaitingLength,
+                0x40
+            ))
+        ));
+
+        assert_eq!(spans[4].0, 20..21);
+        assert_eq!(spans[5].0, 21..28);
+        assert_eq!(spans[5].1.as_ref().unwrap().crc(), 0x0F);
+
+        assert_eq!(spans[6].0, 28..29);
+        assert!(matches!(
+            spans[6].1,
+            Err(SmartAudioError::BufferTooSmall(1))
+        ));
+    }
+
+    #[test]
+    fn test_iter_frames_truncated_frame() {
+        let raw = [0x01, 0xAA, 0x55, 0x09, 0x06, 0x01];
+        let spans: Vec<_> = iter_frames(&raw).collect();
+        assert_eq!(spans.len(), 2);
+        assert_eq!(spans[1].0, 1..6);
+        assert!(matches!(
+            spans[1].1,
+            Err(SmartAudioError::BufferTooSmall(5))
+        ));
+        assert_eq!(iter_frames(&[]).count(), 0);
+    }
+
+    #[test]
+    fn test_find_header_at_every_offset() {
+        for offset in 0..40 {
+            let mut raw = [0x00u8; 42];
+            // Lone first header bytes before the header must not match.
+            for byte in raw.iter_mut().take(offset).step_by(3) {
+                *byte = 0xAA;
+            }
+            raw[offset] = 0xAA;
+            raw[offset + 1] = 0x55;
+            assert_eq!(find_header(&raw), Some(offset));
+            assert_eq!(find_header(&raw[..offset + 1]), None);
+        }
+        assert_eq!(find_header(&[0xAA; 16]), None);
+        assert_eq!(find_header(&[0x55, 0xAA, 0xAA, 0x55]), Some(2));
+    }
+
+    #[test]
+    fn test_host_frames_with_any_config() {
+        let mut parser = SmartAudioParser::<32>::with_config(ParserConfig::ANY);
+        let get_settings = [0xAA, 0x55, 0x03, 0x00, 0x9F];
+        let set_channel = [0xAA, 0x55, 0x07, 0x01, 0x00, 0xB8];
+        let set_mode_response = [0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4F];
+
+        let (consumed, frame) = parser.push_bytes(&get_settings);
+        let frame = frame.unwrap().unwrap();
+        assert_eq!(consumed, 5);
+        assert_eq!(frame.origin(), FrameOrigin::Host);
+        assert!(frame.payload().is_empty());
+
+        let (_, frame) = parser.push_bytes(&set_channel);
+        let frame = frame.unwrap().unwrap();
+        assert_eq!(frame.origin(), FrameOrigin::Host);
+        assert_eq!(frame.payload(), [0x00]);
+
+        let (_, frame) = parser.push_bytes(&set_mode_response);
+        assert_eq!(frame.unwrap().unwrap().origin(), FrameOrigin::Vtx);
+    }
+
+    #[test]
+    fn test_default_config_rejects_host_frames() {
+        let mut parser = SmartAudioParser::new();
+        let (consumed, result) = parser.push_bytes(&[0xAA, 0x55, 0x03, 0x00, 0x9F]);
+        assert_eq!(consumed, 4);
+        assert!(matches!(
+            result,
+            Some(Err(SmartAudioError::UnexpetedDataForState(
+                State::AwaitingLength,
+                0
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_small_buffer_limits_length() {
+        let mut parser = SmartAudioParser::<8>::with_config(ParserConfig::ANY);
+        let set_mode_response = [0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4F];
+        let (_, frame) = parser.push_bytes(&set_mode_response);
+        assert_eq!(frame.unwrap().unwrap().as_bytes(), set_mode_response);
+
+        let frequency_response = [0xAA, 0x55, 0x04, 0x05];
+        let (_, result) = parser.push_bytes(&frequency_response);
+        assert!(matches!(
+            result,
+            Some(Err(SmartAudioError::UnexpetedDataForState(
+                State::AwaitingLength,
+                5
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_large_buffer_accepts_long_frames() {
+        let config = ParserConfig {
+            max_length: 40,
+            ..ParserConfig::VTX
+        };
+        let mut parser = SmartAudioParser::<64>::with_config(config);
+        let mut frame = [0u8; 44];
+        frame[..4].copy_from_slice(&[0xAA, 0x55, 0x11, 40]);
+        frame[43] = Crc8DvbS2::checksum(&frame[2..43]);
+        let (consumed, result) = parser.push_bytes(&frame);
+        assert_eq!(consumed, 44);
+        assert_eq!(result.unwrap().unwrap().len(), 44);
+    }
+
+    #[test]
+    fn test_corrupted_reply_keeps_next_header() {
+        let set_channel = [0xAA, 0x55, 0x07, 0x01, 0x20, 0x1C];
+        // A `SetChannel` reply and a frame with an unknown command byte.
+        for command in [0x03, 0x21] {
+            let mut parser = SmartAudioParser::<32>::with_config(ParserConfig::ANY);
+            let corrupted_reply = [0xAA, 0x55, command, 0x03, 0x20, 0x00, 0x00];
+            let stream: Vec<u8> = corrupted_reply.into_iter().chain(set_channel).collect();
+
+            let (consumed, result) = parser.push_bytes(&stream);
+            assert!(matches!(
+                result,
+                Some(Err(SmartAudioError::InvalidCrc {
+                    frame_crc: 0x00,
+                    ..
+                }))
+            ));
+            let (_, result) = parser.push_bytes(&stream[consumed..]);
+            let frame = result.unwrap().unwrap();
+            assert_eq!(frame.origin(), FrameOrigin::Host);
+            assert_eq!(frame.as_bytes(), set_channel);
+        }
+    }
+
+    #[test]
+    fn test_parser_resets_after_invalid_crc() {
+        let mut parser = SmartAudioParser::new();
+        let corrupted = [0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4E];
+        let (_, result) = parser.push_bytes(&corrupted);
+        assert!(matches!(
+            result,
+            Some(Err(SmartAudioError::InvalidCrc { .. }))
+        ));
+        let valid = [0xAA, 0x55, 0x05, 0x03, 0x0A, 0x01, 0x4F];
+        let (_, result) = parser.push_bytes(&valid);
+        assert!(result.unwrap().is_ok());
+    }
 }
@@ -0,0 +1,298 @@
+use crate::commands::GetSettingsCommand;
+use crate::responses::PowerSettings;
+use crate::responses::Settings;
+use crate::responses::Version;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PresenceConfig {
+    /// Time between two `GetSettings` probes, in milliseconds.
+    pub probe_interval: u32,
+    /// Time to wait for a probe reply before counting it as missed, in milliseconds.
+    pub response_timeout: u32,
+    /// Consecutive answered probes required before reporting the VTX as present.
+    pub connect_after: u8,
+    /// Consecutive missed probes required before reporting the VTX as lost.
+    pub lost_after: u8,
+}
+
+impl Default for PresenceConfig {
+    fn default() -> Self {
+        Self {
+            probe_interval: 1000,
+            response_timeout: 200,
+            connect_after: 1,
+            lost_after: 3,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkState {
+    #[default]
+    Searching,
+    Connected,
+    Lost,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PresenceEvent {
+    /// First time a VTX answered since the supervisor was created.
+    Connected(Settings),
+    /// The VTX stopped answering probes.
+    Lost,
+    /// The same VTX answers again after being lost.
+    Reconnected(Settings),
+    /// A VTX answers again after being lost, but reports a different identity.
+    Swapped {
+        previous: Settings,
+        current: Settings,
+    },
+}
+
+/// Periodic `GetSettings` prober that tracks whether a VTX is attached.
+///
+/// The supervisor never touches the serial port. The host calls [`Self::probe`]
+/// from its main loop and sends the returned command, then reports the outcome
+/// through [`Self::on_settings`], [`Self::on_error`] or by calling
+/// [`Self::poll`], which turns unanswered probes into misses.
+#[derive(Debug, Clone)]
+pub struct PresenceSupervisor {
+    config: PresenceConfig,
+    state: LinkState,
+    probe_sent_at: Option<u32>,
+    last_probe_at: Option<u32>,
+    successes: u8,
+    failures: u8,
+    known: Option<Settings>,
+}
+
+impl PresenceSupervisor {
+    pub fn new(config: PresenceConfig) -> Self {
+        Self {
+            config,
+            state: LinkState::Searching,
+            probe_sent_at: None,
+            last_probe_at: None,
+            successes: 0,
+            failures: 0,
+            known: None,
+        }
+    }
+
+    pub fn state(&self) -> LinkState {
+        self.state
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state == LinkState::Connected
+    }
+
+    /// Settings of the VTX last seen by the supervisor.
+    pub fn settings(&self) -> Option<&Settings> {
+        self.known.as_ref()
+    }
+
+    pub fn is_probe_pending(&self) -> bool {
+        self.probe_sent_at.is_some()
+    }
+
+    /// Returns a probe command when one is due, and marks it as sent at `now`.
+    pub fn probe(&mut self, now: u32) -> Option<GetSettingsCommand> {
+        if self.probe_sent_at.is_some() {
+            return None;
+        }
+        let due = self
+            .last_probe_at
+            .is_none_or(|t| now.wrapping_sub(t) >= self.config.probe_interval);
+        if !due {
+            return None;
+        }
+        self.probe_sent_at = Some(now);
+        self.last_probe_at = Some(now);
+        Some(GetSettingsCommand {})
+    }
+
+    /// Counts the outstanding probe as missed once its timeout has elapsed.
+    pub fn poll(&mut self, now: u32) -> Option<PresenceEvent> {
+        let sent_at = self.probe_sent_at?;
+        if now.wrapping_sub(sent_at) < self.config.response_timeout {
+            return None;
+        }
+        self.miss()
+    }
+
+    /// Reports a `GetSettings` reply to the outstanding probe.
+    pub fn on_settings(&mut self, settings: &Settings) -> Option<PresenceEvent> {
+        self.probe_sent_at = None;
+        self.failures = 0;
+        self.successes = self.successes.saturating_add(1);
+
+        if self.state == LinkState::Connected {
+            self.known = Some(*settings);
+            return None;
+        }
+        if self.successes < self.config.connect_after {
+            return None;
+        }
+
+        let previous = self.known.replace(*settings);
+        let event = match (self.state, previous) {
+            (LinkState::Lost, Some(previous)) if !same_vtx(&previous, settings) => {
+                PresenceEvent::Swapped {
+                    previous,
+                    current: *settings,
+                }
+            }
+            (LinkState::Lost, Some(_)) => PresenceEvent::Reconnected(*settings),
+            _ => PresenceEvent::Connected(*settings),
+        };
+        self.state = LinkState::Connected;
+        Some(event)
+    }
+
+    /// Reports a probe that got a corrupted or unexpected reply.
+    pub fn on_error(&mut self) -> Option<PresenceEvent> {
+        self.miss()
+    }
+
+    fn miss(&mut self) -> Option<PresenceEvent> {
+        self.probe_sent_at = None;
+        self.successes = 0;
+        self.failures = self.failures.saturating_add(1);
+        if self.state == LinkState::Connected && self.failures >= self.config.lost_after {
+            self.state = LinkState::Lost;
+            return Some(PresenceEvent::Lost);
+        }
+        None
+    }
+}
+
+impl Default for PresenceSupervisor {
+    fn default() -> Self {
+        Self::new(PresenceConfig::default())
+    }
+}
+
+/// Compares the parts of the settings that identify a VTX rather than its
+/// current configuration: protocol version, power table and frequency.
+fn same_vtx(a: &Settings, b: &Settings) -> bool {
+    fn power_table(settings: &Settings) -> Option<PowerSettings> {
+        settings.power_settings.map(|p| PowerSettings {
+            current_power: 0,
+            ..p
+        })
+    }
+
+    let version_matches = a.version == b.version && a.version != Version::Unknown;
+    version_matches && power_table(a) == power_table(b) && a.frequency == b.frequency
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(version: Version, frequency: u16) -> Settings {
+        Settings {
+            version,
+            frequency,
+            ..Default::default()
+        }
+    }
+
+    fn connected() -> PresenceSupervisor {
+        let mut supervisor = PresenceSupervisor::default();
+        assert!(supervisor.probe(0).is_some());
+        let event = supervisor.on_settings(&settings(Version::V2_0, 5865));
+        assert_eq!(
+            event,
+            Some(PresenceEvent::Connected(settings(Version::V2_0, 5865)))
+        );
+        supervisor
+    }
+
+    fn lose(supervisor: &mut PresenceSupervisor, start: u32) -> u32 {
+        let mut now = start;
+        for _ in 0..3 {
+            now += 1000;
+            assert!(supervisor.probe(now).is_some());
+            assert_eq!(supervisor.poll(now + 100), None);
+            let event = supervisor.poll(now + 200);
+            if event.is_some() {
+                assert_eq!(event, Some(PresenceEvent::Lost));
+            }
+        }
+        assert_eq!(supervisor.state(), LinkState::Lost);
+        now
+    }
+
+    #[test]
+    fn test_probe_schedule() {
+        let mut supervisor = PresenceSupervisor::default();
+        assert!(supervisor.probe(0).is_some());
+        assert!(supervisor.probe(10).is_none());
+        supervisor.on_settings(&settings(Version::V2_0, 5865));
+        assert!(supervisor.probe(500).is_none());
+        assert!(supervisor.probe(1000).is_some());
+    }
+
+    #[test]
+    fn test_lost_requires_consecutive_misses() {
+        let mut supervisor = connected();
+        assert!(supervisor.probe(1000).is_some());
+        assert_eq!(supervisor.poll(1200), None);
+        assert!(supervisor.probe(2000).is_some());
+        assert_eq!(supervisor.on_settings(&settings(Version::V2_0, 5865)), None);
+        assert_eq!(supervisor.state(), LinkState::Connected);
+        lose(&mut supervisor, 2000);
+    }
+
+    #[test]
+    fn test_reconnected() {
+        let mut supervisor = connected();
+        let now = lose(&mut supervisor, 0);
+        assert!(supervisor.probe(now + 1000).is_some());
+        let event = supervisor.on_settings(&settings(Version::V2_0, 5865));
+        assert_eq!(
+            event,
+            Some(PresenceEvent::Reconnected(settings(Version::V2_0, 5865)))
+        );
+        assert!(supervisor.is_connected());
+    }
+
+    #[test]
+    fn test_swap_detected() {
+        let mut supervisor = connected();
+        let now = lose(&mut supervisor, 0);
+        assert!(supervisor.probe(now + 1000).is_some());
+        let event = supervisor.on_settings(&settings(Version::V2_1, 5865));
+        assert_eq!(
+            event,
+            Some(PresenceEvent::Swapped {
+                previous: settings(Version::V2_0, 5865),
+                current: settings(Version::V2_1, 5865),
+            })
+        );
+    }
+
+    #[test]
+    fn test_connect_hysteresis() {
+        let mut supervisor = PresenceSupervisor::new(PresenceConfig {
+            connect_after: 2,
+            ..Default::default()
+        });
+        assert!(supervisor.probe(0).is_some());
+        assert_eq!(supervisor.on_settings(&settings(Version::V2_0, 5865)), None);
+        assert!(supervisor.probe(1000).is_some());
+        assert_eq!(supervisor.on_error(), None);
+        assert!(supervisor.probe(2000).is_some());
+        assert_eq!(supervisor.on_settings(&settings(Version::V2_0, 5865)), None);
+        assert!(supervisor.probe(3000).is_some());
+        assert!(matches!(
+            supervisor.on_settings(&settings(Version::V2_0, 5865)),
+            Some(PresenceEvent::Connected(_))
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::AtomicU32;
+use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::Ordering;
+
+use crate::parser::OwnedFrame;
+use crate::parser::SmartAudioError;
+use crate::parser::SmartAudioParser;
+use crate::responses::Response;
+
+/// Fixed capacity single producer, single consumer queue between an
+/// interrupt handler and a task.
+///
+/// Only atomic loads and stores are used, no compare-and-swap, so the queue
+/// works on cores without CAS such as `thumbv6m`. The producer half parses
+/// bytes in the interrupt and the consumer half dequeues complete items.
+pub struct FrameQueue<T, const N: usize> {
+    slots: [UnsafeCell<MaybeUninit<T>>; N],
+    /// Number of items ever enqueued, written by the producer only.
+    head: AtomicUsize,
+    /// Number of items ever dequeued, written by the consumer only.
+    tail: AtomicUsize,
+    /// Items dropped because the queue was full, written by the producer only.
+    overflows: AtomicU32,
+}
+
+// SAFETY: the producer only writes slots the consumer has released and the
+// consumer only reads slots the producer has published, ordered by the
+// acquire/release pairs on `head` and `tail`.
+unsafe impl<T: Copy + Send, const N: usize> Sync for FrameQueue<T, N> {}
+
+impl<T: Copy, const N: usize> FrameQueue<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            overflows: AtomicU32::new(0),
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Splits the queue into its interrupt and task halves.
+    pub fn split(&mut self) -> (Producer<'_, T, N>, Consumer<'_, T, N>) {
+        (
+            Producer {
+                queue: self,
+                parser: SmartAudioParser::new(),
+            },
+            Consumer { queue: self },
+        )
+    }
+}
+
+impl<T: Copy, const N: usize> Default for FrameQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Producer<'a, T, const N: usize> {
+    queue: &'a FrameQueue<T, N>,
+    parser: SmartAudioParser,
+}
+
+impl<T: Copy, const N: usize> Producer<'_, T, N> {
+    /// Enqueues `item`, or hands it back and counts an overflow when full.
+    pub fn enqueue(&mut self, item: T) -> Result<(), T> {
+        let queue = self.queue;
+        let head = queue.head.load(Ordering::Relaxed);
+        let tail = queue.tail.load(Ordering::Acquire);
+        if head.wrapping_sub(tail) >= N {
+            let overflows = queue.overflows.load(Ordering::Relaxed);
+            queue
+                .overflows
+                .store(overflows.wrapping_add(1), Ordering::Relaxed);
+            return Err(item);
+        }
+        // SAFETY: the slot is not visible to the consumer until `head` is published.
+        unsafe { (*queue.slots[head % N].get()).write(item) };
+        queue.head.store(head.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    pub fn is_full(&self) -> bool {
+        let head = self.queue.head.load(Ordering::Relaxed);
+        head.wrapping_sub(self.queue.tail.load(Ordering::Acquire)) >= N
+    }
+}
+
+impl<const N: usize> Producer<'_, OwnedFrame, N> {
+    /// Parses `byte` and enqueues the frame it completes, if any.
+    pub fn push_byte(&mut self, byte: u8) -> Result<(), SmartAudioError> {
+        if let Some(raw_frame) = self.parser.push_byte_raw(byte)? {
+            let frame = OwnedFrame::try_from(&raw_frame)?;
+            let _ = self.enqueue(frame);
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Producer<'_, Response, N> {
+    /// Parses `byte` and enqueues the response it completes, if any.
+    pub fn push_byte(&mut self, byte: u8) -> Result<(), SmartAudioError> {
+        if let Some(response) = self.parser.push_byte(byte)? {
+            let _ = self.enqueue(response);
+        }
+        Ok(())
+    }
+}
+
+pub struct Consumer<'a, T, const N: usize> {
+    queue: &'a FrameQueue<T, N>,
+}
+
+impl<T: Copy, const N: usize> Consumer<'_, T, N> {
+    pub fn dequeue(&mut self) -> Option<T> {
+        let queue = self.queue;
+        let tail = queue.tail.load(Ordering::Relaxed);
+        if queue.head.load(Ordering::Acquire) == tail {
+            return None;
+        }
+        // SAFETY: the producer published this slot before advancing `head`.
+        let item = unsafe { (*queue.slots[tail % N].get()).assume_init_read() };
+        queue.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Some(item)
+    }
+
+    pub fn len(&self) -> usize {
+        let tail = self.queue.tail.load(Ordering::Relaxed);
+        self.queue.head.load(Ordering::Acquire).wrapping_sub(tail)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Items dropped by the producer because the queue was full.
+    pub fn overflows(&self) -> u32 {
+        self.queue.overflows.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+
+    const SET_CHANNEL: [u8; 7] = [0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A];
+
+    #[test]
+    fn test_frames_cross_queue() {
+        let mut queue: FrameQueue<OwnedFrame, 2> = FrameQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        assert_eq!(consumer.dequeue(), None);
+
+        for _ in 0..3 {
+            for byte in SET_CHANNEL {
+                producer.push_byte(byte).unwrap();
+            }
+        }
+        assert!(producer.is_full());
+        assert_eq!(consumer.len(), 2);
+        assert_eq!(consumer.overflows(), 1);
+        assert_eq!(consumer.dequeue().unwrap().as_bytes(), SET_CHANNEL);
+        assert_eq!(consumer.dequeue().unwrap().as_bytes(), SET_CHANNEL);
+        assert!(consumer.is_empty());
+    }
+
+    #[test]
+    fn test_responses_cross_threads() {
+        let mut queue: FrameQueue<Response, 4> = FrameQueue::new();
+        let (mut producer, mut consumer) = queue.split();
+        std::thread::scope(|scope| {
+            scope.spawn(move || {
+                for _ in 0..100 {
+                    while producer.is_full() {
+                        std::thread::yield_now();
+                    }
+                    for byte in SET_CHANNEL {
+                        producer.push_byte(byte).unwrap();
+                    }
+                }
+            });
+            let mut received = 0;
+            while received < 100 {
+                match consumer.dequeue() {
+                    Some(response) => {
+                        assert!(matches!(response, Response::SetChannel(r) if r.channel == 0));
+                        received += 1;
+                    }
+                    None => std::thread::yield_now(),
+                }
+            }
+            assert_eq!(consumer.overflows(), 0);
+        });
+    }
+}
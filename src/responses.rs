@@ -1,6 +1,14 @@
 use crate::constants::get_settings_flags;
 use crate::constants::mode_flags;
 use crate::constants::response as resp;
+use crate::constants::HEADER_BYTE_1;
+use crate::constants::MAX_FRAME_SIZE;
+use crate::layout::smartaudio_frames;
+use crate::layout::FrameLayout;
+use crate::parser::find_header;
+use crate::parser::scan_frame;
+use crate::parser::FrameOrigin;
+use crate::parser::Scan;
 use crate::SmartAudioParser;
 use crate::{parser::SmartAudioError, RawSmartAudioFrame};
 
@@ -14,6 +22,17 @@ pub enum Version {
     Unknown,
 }
 
+impl Version {
+    /// `GetSettings` response command byte announcing this version.
+    pub const fn settings_command(self) -> u8 {
+        match self {
+            Self::V1_0 => resp::GET_SETTINGS_V1_0,
+            Self::V2_1 => resp::GET_SETTINGS_V2_1,
+            Self::V2_0 | Self::Unknown => resp::GET_SETTINGS_V2_0,
+        }
+    }
+}
+
 impl From<u8> for Version {
     fn from(v: u8) -> Self {
         match v {
@@ -27,6 +46,9 @@ impl From<u8> for Version {
     }
 }
 
+/// Decoding of a VTX frame with a known command. Panics on a payload too
+/// short for it, [`Response::parse`] reports that as
+/// [`SmartAudioError::InvalidPayloadLength`] instead.
 pub trait SmartAudioReponse {
     fn from_raw_frame(raw_frame: &RawSmartAudioFrame<'_>) -> Self;
 }
@@ -42,132 +64,66 @@ pub struct PowerSettings {
     pub dbm_level_4: u8,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct Settings {
-    pub version: Version,
-    pub channel: u8,
-    pub power_level: u8,
-    pub frequency: u16,
-    pub unlocked: bool,
-    pub user_frequency_mode: bool,
-    pub pitmode_enabled: bool,
-    pub pitmode_in_range_active: bool,
-    pub pitmode_out_range_active: bool,
-    pub power_settings: Option<PowerSettings>,
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SetPowerResponse {
-    power: u8,
-}
-
-impl SmartAudioReponse for SetPowerResponse {
-    fn from_raw_frame(raw_frame: &RawSmartAudioFrame<'_>) -> Self {
-        Self {
-            power: raw_frame.payload()[0],
-        }
+smartaudio_frames! {
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Vtx struct Settings(resp::GET_SETTINGS_V2_0) {
+        pub version: Version = command,
+        pub channel: u8,
+        pub power_level: u8,
+        flags {
+            pub user_frequency_mode = get_settings_flags::USER_FREQUENCY,
+            pub pitmode_enabled = get_settings_flags::PITMODE_ENABLED,
+            pub pitmode_in_range_active = get_settings_flags::PITMODE_IN_RANGE,
+            pub pitmode_out_range_active = get_settings_flags::PITMODE_OUT_RANGE,
+            pub unlocked = get_settings_flags::UNLOCKED,
+        },
+        pub frequency: u16,
+        pub power_settings: Option<PowerSettings> if version == Version::V2_1 => {
+            current_power: u8,
+            num_power_levels: u8,
+            dbm_level_1: u8,
+            dbm_level_2: u8,
+            dbm_level_3: u8,
+            dbm_level_4: u8,
+        },
     }
-}
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SetChannelResponse {
-    channel: u8,
-}
-
-impl SmartAudioReponse for SetChannelResponse {
-    fn from_raw_frame(raw_frame: &RawSmartAudioFrame<'_>) -> Self {
-        Self {
-            channel: raw_frame.payload()[0],
-        }
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Vtx struct SetPowerResponse(resp::SET_POWER) {
+        pub(crate) power: u8,
+        reserved: Reserved,
     }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SetFrequencyResponse {
-    frequency: u16,
-}
 
-impl SmartAudioReponse for SetFrequencyResponse {
-    fn from_raw_frame(raw_frame: &RawSmartAudioFrame<'_>) -> Self {
-        let buffer = raw_frame.payload();
-        Self {
-            frequency: u16::from_be_bytes([buffer[0], buffer[1]]),
-        }
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Vtx struct SetChannelResponse(resp::SET_CHANNEL) {
+        pub(crate) channel: u8,
+        reserved: Reserved,
     }
-}
-
-#[derive(Clone, Copy, Debug, PartialEq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct SetModeResponse {
-    pitmode_in_range_active: bool,
-    pitmode_out_range_active: bool,
-    pitmode_enabled: bool,
-    unlocked: bool,
-}
 
-impl SmartAudioReponse for SetModeResponse {
-    fn from_raw_frame(raw_frame: &RawSmartAudioFrame<'_>) -> Self {
-        let mode = raw_frame.payload()[0];
-        Self {
-            pitmode_in_range_active: mode & mode_flags::PITMODE_IN_RANGE != 0,
-            pitmode_out_range_active: mode & mode_flags::PITMODE_OUT_RANGE != 0,
-            pitmode_enabled: mode & mode_flags::PITMODE_ENABLED != 0,
-            unlocked: mode & mode_flags::UNLOCKED != 0,
-        }
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Vtx struct SetFrequencyResponse(resp::SET_FREQUENCY) {
+        pub(crate) frequency: u16,
+        reserved: Reserved,
     }
-}
 
-impl SmartAudioReponse for Settings {
-    fn from_raw_frame(raw_frame: &RawSmartAudioFrame<'_>) -> Self {
-        let b = raw_frame.payload();
-
-        let version = Version::from(raw_frame.commnand());
-        let channel = b[0];
-        let power_level = b[1];
-
-        // unpack mode
-        let mode = b[2];
-        let pitmode_enabled = mode & get_settings_flags::PITMODE_ENABLED != 0;
-        let pitmode_in_range_active = mode & get_settings_flags::PITMODE_IN_RANGE != 0;
-        let pitmode_out_range_active = mode & get_settings_flags::PITMODE_OUT_RANGE != 0;
-        let unlocked = mode & get_settings_flags::UNLOCKED != 0;
-        let user_frequency_mode = mode & get_settings_flags::USER_FREQUENCY != 0;
-
-        let frequency = u16::from_be_bytes([b[3], b[4]]);
-
-        let power_settings = if version == Version::V2_1 {
-            Some(PowerSettings {
-                current_power: b[5],
-                num_power_levels: b[6],
-                dbm_level_1: b[7],
-                dbm_level_2: b[8],
-                dbm_level_3: b[9],
-                dbm_level_4: b[10],
-            })
-        } else {
-            None
-        };
-
-        Self {
-            version,
-            channel,
-            power_level,
-            frequency,
-            unlocked,
-            user_frequency_mode,
-            pitmode_enabled,
-            pitmode_in_range_active,
-            pitmode_out_range_active,
-            power_settings,
-        }
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    Vtx struct SetModeResponse(resp::SET_MODE) {
+        flags {
+            pub(crate) pitmode_in_range_active = mode_flags::PITMODE_IN_RANGE,
+            pub(crate) pitmode_out_range_active = mode_flags::PITMODE_OUT_RANGE,
+            pub(crate) pitmode_enabled = mode_flags::PITMODE_ENABLED,
+            pub(crate) unlocked = mode_flags::UNLOCKED,
+        },
+        reserved: Reserved,
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Response {
     GetSettings(Settings),
@@ -181,49 +137,60 @@ pub enum Response {
 impl Response {
     pub fn parse(raw_frame: &RawSmartAudioFrame<'_>) -> Result<Self, SmartAudioError> {
         let cmd = raw_frame.commnand();
+        if raw_frame.origin() == FrameOrigin::Host {
+            return Err(SmartAudioError::UnknownCommand(cmd));
+        }
+        let payload = raw_frame.payload();
         match cmd {
             resp::GET_SETTINGS_V1_0 | resp::GET_SETTINGS_V2_0 | resp::GET_SETTINGS_V2_1 => {
-                Ok(Self::GetSettings(Settings::from_raw_frame(raw_frame)))
+                Settings::decode(cmd, payload).map(Self::GetSettings)
             }
-            resp::SET_POWER => Ok(Self::SetPower(SetPowerResponse::from_raw_frame(raw_frame))),
-            resp::SET_CHANNEL => Ok(Self::SetChannel(SetChannelResponse::from_raw_frame(
-                raw_frame,
-            ))),
-            resp::SET_FREQUENCY => Ok(Self::SetFrequency(SetFrequencyResponse::from_raw_frame(
-                raw_frame,
-            ))),
-            resp::SET_MODE => Ok(Self::SetMode(SetModeResponse::from_raw_frame(raw_frame))),
+            resp::SET_POWER => SetPowerResponse::decode(cmd, payload).map(Self::SetPower),
+            resp::SET_CHANNEL => SetChannelResponse::decode(cmd, payload).map(Self::SetChannel),
+            resp::SET_FREQUENCY => {
+                SetFrequencyResponse::decode(cmd, payload).map(Self::SetFrequency)
+            }
+            resp::SET_MODE => SetModeResponse::decode(cmd, payload).map(Self::SetMode),
             _ => Err(SmartAudioError::InvalidHeader),
         }
     }
 }
 
-impl SmartAudioParser {
-    pub fn iter_responses<'a, 'b>(&'a mut self, buffer: &'b [u8]) -> ResponseIterator<'a, 'b> {
+impl<const N: usize> SmartAudioParser<N> {
+    /// Iterates over the responses in `buffer`, continuing any frame left
+    /// incomplete by the previous call.
+    ///
+    /// Yields the same items as calling [`SmartAudioParser::push_byte`] for
+    /// each byte, so every garbage byte between frames yields its own
+    /// [`SmartAudioError::UnexpetedDataForState`] error.
+    pub fn iter_responses<'a, 'b>(&'a mut self, buffer: &'b [u8]) -> ResponseIterator<'a, 'b, N> {
         ResponseIterator {
             parser: self,
             buffer,
             position: 0,
+            garbage_end: 0,
         }
     }
 }
 
-pub struct ResponseIterator<'a, 'b> {
-    parser: &'a mut SmartAudioParser,
+pub struct ResponseIterator<'a, 'b, const N: usize = { MAX_FRAME_SIZE }> {
+    parser: &'a mut SmartAudioParser<N>,
     buffer: &'b [u8],
     position: usize,
+    garbage_end: usize,
 }
 
-impl Iterator for ResponseIterator<'_, '_> {
-    type Item = Result<Response, SmartAudioError>;
-
-    fn next(&mut self) -> Option<Self::Item> {
+impl<const N: usize> ResponseIterator<'_, '_, N> {
+    /// Hands bytes to the parser one at a time until it completes a frame
+    /// or is back to waiting for a header.
+    fn next_bytewise(&mut self) -> Option<Result<Response, SmartAudioError>> {
         while self.position < self.buffer.len() {
             let byte = self.buffer[self.position];
             self.position += 1;
 
             match self.parser.push_byte(byte) {
                 Ok(Some(response)) => return Some(Ok(response)),
+                Ok(None) if self.parser.is_idle() => return None,
                 Ok(None) => (),
                 Err(e) => return Some(Err(e)),
             }
@@ -232,7 +199,59 @@ impl Iterator for ResponseIterator<'_, '_> {
     }
 }
 
-impl SmartAudioParser {
+/// Frames found in the slice are validated and parsed in place, only a frame
+/// cut off at the end of the slice goes through the parser buffer so it can
+/// be completed by the next call. Bytes between frames still go through the
+/// parser so each of them is reported as it would be byte by byte.
+impl<const N: usize> Iterator for ResponseIterator<'_, '_, N> {
+    type Item = Result<Response, SmartAudioError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.buffer.len() {
+            while self.position < self.garbage_end {
+                let byte = self.buffer[self.position];
+                self.position += 1;
+                if let Err(e) = self.parser.push_byte(byte) {
+                    return Some(Err(e));
+                }
+            }
+
+            if !self.parser.is_idle() {
+                match self.next_bytewise() {
+                    Some(result) => return Some(result),
+                    None => continue,
+                }
+            }
+
+            let rest = &self.buffer[self.position..];
+            let garbage = match find_header(rest) {
+                Some(offset) => offset,
+                // Keep a trailing first header byte for the next call.
+                None if rest.last() == Some(&HEADER_BYTE_1) => rest.len() - 1,
+                None => rest.len(),
+            };
+            if garbage > 0 {
+                self.garbage_end = self.position + garbage;
+                continue;
+            }
+
+            match scan_frame::<N>(self.parser.config(), rest) {
+                Scan::Frame(frame) => {
+                    self.position += frame.len();
+                    return Some(Response::parse(&frame));
+                }
+                Scan::Error { len, error } => {
+                    self.position += len;
+                    return Some(Err(error));
+                }
+                Scan::Incomplete => return self.next_bytewise(),
+            }
+        }
+        None
+    }
+}
+
+impl<const N: usize> SmartAudioParser<N> {
     pub fn push_byte(&mut self, byte: u8) -> Result<Option<Response>, SmartAudioError> {
         let Some(raw_packet) = self.push_byte_raw(byte)? else {
             return Ok(None);
@@ -247,8 +266,27 @@ mod tests {
     use super::*;
     use crate::parser::RawSmartAudioFrame;
     use crate::parser::SmartAudioError;
+    use crate::parser::State;
     use std::vec::Vec;
 
+    #[test]
+    fn test_from_raw_frame() {
+        let raw = [0xAA, 0x55, 0x04, 0x04, 0x16, 0xE9, 0x01, 0x00];
+        let frame = RawSmartAudioFrame::new(&raw).unwrap();
+        assert_eq!(
+            SetFrequencyResponse::from_raw_frame(&frame),
+            SetFrequencyResponse { frequency: 5865 }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "payload too short for Settings")]
+    fn test_from_raw_frame_short_payload() {
+        let raw = [0xAA, 0x55, 0x09, 0x03, 0x01, 0x00, 0x00];
+        let frame = RawSmartAudioFrame::new(&raw).unwrap();
+        Settings::from_raw_frame(&frame);
+    }
+
     #[test]
     fn test_get_settings_v1_0_parsing() {
         let raw: [u8; 10] = [0xAA, 0x55, 0x01, 0x06, 0x00, 0x00, 0x01, 0x16, 0xE9, 0x4D];
@@ -490,4 +528,79 @@ mod tests {
         assert!(matches!(&responses[6], Response::SetFrequency(actual) if actual == &frame6));
         assert!(matches!(&responses[7], Response::SetMode(actual) if actual == &frame7));
     }
+
+    #[test]
+    fn test_iter_responses_across_calls() {
+        let set_channel = [0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A];
+        let mut raw = Vec::from([0x00, 0x01, 0x02, 0xAA, 0x00]);
+        raw.extend(set_channel);
+        raw.extend(&set_channel[..3]);
+
+        let mut parser = SmartAudioParser::new();
+        let results: Vec<_> = parser.iter_responses(&raw).collect();
+        assert_eq!(results.len(), 5);
+        for (result, byte) in results[..3].iter().zip([0x00, 0x01, 0x02]) {
+            assert_eq!(
+                *result,
+                Err(SmartAudioError::UnexpetedDataForState(
+                    State::AwaitingHeader1,
+                    byte
+                ))
+            );
+        }
+        assert_eq!(
+            results[3],
+            Err(SmartAudioError::UnexpetedDataForState(
+                State::AwaitingHeader2,
+                0x00
+            ))
+        );
+        assert!(matches!(&results[4], Ok(Response::SetChannel(r)) if r.channel == 0));
+
+        let results: Vec<_> = parser.iter_responses(&set_channel[3..]).collect();
+        assert_eq!(results.len(), 1);
+        assert!(matches!(&results[0], Ok(Response::SetChannel(_))));
+
+        // A first header byte at the end of a slice is kept for the next one.
+        let results: Vec<_> = parser.iter_responses(&[0x01, 0xAA]).collect();
+        assert_eq!(results.len(), 1);
+        let results: Vec<_> = parser.iter_responses(&set_channel[1..]).collect();
+        assert!(matches!(&results[..], [Ok(Response::SetChannel(_))]));
+    }
+
+    #[test]
+    fn test_iter_responses_matches_push_byte() {
+        let raw = [
+            0x00, 0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x4A, 0x55, 0xAA, 0xAA, 0x55, 0x05, 0x03,
+            0x0A, 0x01, 0x4F, 0xAA, 0x55, 0x03, 0x03, 0x00, 0x01, 0x00, 0x7F, 0xAA,
+        ];
+        let mut parser = SmartAudioParser::new();
+        let expected: Vec<_> = raw
+            .iter()
+            .filter_map(|byte| parser.push_byte(*byte).transpose())
+            .collect();
+        let mut parser = SmartAudioParser::new();
+        let results: Vec<_> = parser.iter_responses(&raw).collect();
+        assert_eq!(results, expected);
+        assert!(!parser.is_idle());
+    }
+
+    #[test]
+    fn test_short_payload_is_rejected() {
+        let mut parser = SmartAudioParser::<32>::with_config(crate::parser::ParserConfig::ANY);
+        // SetFrequency response with a single payload byte.
+        let raw = [0xAA, 0x55, 0x04, 0x02, 0x16, 0xB6];
+        let frame = parser.push_bytes(&raw).1.unwrap().unwrap();
+        assert!(matches!(
+            Response::parse(&frame.as_raw()),
+            Err(SmartAudioError::InvalidPayloadLength)
+        ));
+
+        let host_frame = [0xAA, 0x55, 0x03, 0x00, 0x9F];
+        let frame = parser.push_bytes(&host_frame).1.unwrap().unwrap();
+        assert!(matches!(
+            Response::parse(&frame.as_raw()),
+            Err(SmartAudioError::UnknownCommand(0x03))
+        ));
+    }
 }
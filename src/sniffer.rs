@@ -0,0 +1,233 @@
+use crate::commands::Command;
+use crate::parser::FrameOrigin;
+use crate::parser::ParserConfig;
+use crate::parser::SmartAudioError;
+use crate::parser::SmartAudioParser;
+use crate::responses::Response;
+
+/// Step of the transcript of a sniffed line, timestamps in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Exchange {
+    /// Command followed by its reply.
+    Transaction {
+        command: Command,
+        response: Response,
+        sent_at: u32,
+        replied_at: u32,
+    },
+    /// Command without a reply before the next command or the timeout.
+    Unanswered { command: Command, sent_at: u32 },
+    /// Reply without a pending command of the same kind.
+    Unsolicited { response: Response, at: u32 },
+    /// Frame with a bad CRC, or a valid frame that could not be decoded.
+    Error { error: SmartAudioError, at: u32 },
+}
+
+/// Passive decoder for a tapped `SmartAudio` wire carrying both directions.
+///
+/// Frames are told apart by their command byte and length, and by their CRC
+/// style for unknown commands, see [`FrameOrigin`]. Host frames decode into a
+/// [`Command`] and VTX frames into a [`Response`], and each reply is paired
+/// with the command before it. Bytes between frames are skipped silently.
+#[derive(Debug)]
+pub struct Sniffer {
+    parser: SmartAudioParser,
+    pending: Option<(Command, u32)>,
+    reply_timeout: u32,
+}
+
+impl Sniffer {
+    /// `reply_timeout` in milliseconds after which a command is unanswered.
+    pub fn new(reply_timeout: u32) -> Self {
+        Self {
+            parser: SmartAudioParser::with_config(ParserConfig::ANY),
+            pending: None,
+            reply_timeout,
+        }
+    }
+
+    pub fn push_byte(&mut self, byte: u8, now: u32) -> Option<Exchange> {
+        let frame = match self.parser.push_byte_raw(byte) {
+            Ok(Some(frame)) => frame,
+            Ok(None) | Err(SmartAudioError::UnexpetedDataForState(..)) => return None,
+            Err(error) => return Some(Exchange::Error { error, at: now }),
+        };
+        match frame.origin() {
+            FrameOrigin::Host => match Command::parse(&frame) {
+                Ok(command) => self
+                    .pending
+                    .replace((command, now))
+                    .map(|(command, sent_at)| Exchange::Unanswered { command, sent_at }),
+                Err(error) => Some(Exchange::Error { error, at: now }),
+            },
+            FrameOrigin::Vtx => match Response::parse(&frame) {
+                Ok(response) => Some(self.on_response(response, now)),
+                Err(error) => Some(Exchange::Error { error, at: now }),
+            },
+        }
+    }
+
+    fn on_response(&mut self, response: Response, now: u32) -> Exchange {
+        match self.pending {
+            Some((command, sent_at)) if command.is_answered_by(&response) => {
+                self.pending = None;
+                Exchange::Transaction {
+                    command,
+                    response,
+                    sent_at,
+                    replied_at: now,
+                }
+            }
+            _ => Exchange::Unsolicited { response, at: now },
+        }
+    }
+
+    /// Reports the pending command as unanswered once the timeout passed.
+    pub fn poll(&mut self, now: u32) -> Option<Exchange> {
+        let (_, sent_at) = self.pending?;
+        if now.wrapping_sub(sent_at) < self.reply_timeout {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// Reports the pending command as unanswered, e.g. at the end of a capture.
+    pub fn flush(&mut self) -> Option<Exchange> {
+        self.pending
+            .take()
+            .map(|(command, sent_at)| Exchange::Unanswered { command, sent_at })
+    }
+}
+
+impl Default for Sniffer {
+    fn default() -> Self {
+        Self::new(200)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::builder::RawFrameBuilder;
+    use crate::commands::GetSettingsCommand;
+    use crate::commands::Power;
+    use crate::commands::SetChannelCommand;
+    use crate::commands::SetFrequencyCommand;
+    use crate::commands::SetModeCommand;
+    use crate::commands::SetPowerCommand;
+    use crate::commands::SmartAudioCommand;
+    use crate::constants::command;
+    use std::vec::Vec;
+
+    fn sniff(sniffer: &mut Sniffer, bytes: &[u8], at: u32) -> Vec<Exchange> {
+        bytes
+            .iter()
+            .filter_map(|byte| sniffer.push_byte(*byte, at))
+            .collect()
+    }
+
+    const SETTINGS: [u8; 10] = [0xAA, 0x55, 0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9, 0x0A];
+
+    #[test]
+    fn test_pairs_commands_and_replies() {
+        let mut sniffer = Sniffer::default();
+        let get_settings = GetSettingsCommand {}.to_array();
+        assert!(sniff(&mut sniffer, &get_settings, 10).is_empty());
+        let exchanges = sniff(&mut sniffer, &SETTINGS, 80);
+        assert!(matches!(
+            exchanges[..],
+            [Exchange::Transaction {
+                command: Command::GetSettings(_),
+                response: Response::GetSettings(settings),
+                sent_at: 10,
+                replied_at: 80,
+            }] if settings.channel == 1
+        ));
+
+        let set_channel = SetChannelCommand { channel: 3 }.to_array();
+        assert!(sniff(&mut sniffer, &set_channel, 100).is_empty());
+        let exchanges = sniff(&mut sniffer, &get_settings, 150);
+        assert_eq!(
+            exchanges,
+            [Exchange::Unanswered {
+                command: Command::SetChannel(SetChannelCommand { channel: 3 }),
+                sent_at: 100,
+            }]
+        );
+        assert_eq!(sniffer.poll(200), None);
+        assert!(matches!(
+            sniffer.poll(400),
+            Some(Exchange::Unanswered {
+                command: Command::GetSettings(_),
+                sent_at: 150
+            })
+        ));
+    }
+
+    #[test]
+    fn test_commands_with_vtx_crc_collisions() {
+        // The byte after each payload happens to match the VTX style CRC.
+        let mut frames = [0u8; 7 * 7];
+        let mut len = 0;
+        let commands = [
+            Command::SetChannel(SetChannelCommand { channel: 0xE4 }),
+            Command::SetPower(SetPowerCommand {
+                power: Power::from_byte(0xF2),
+            }),
+            Command::SetFrequency(SetFrequencyCommand { frequency: 5003 }),
+            Command::SetFrequency(SetFrequencyCommand { frequency: 5343 }),
+            Command::SetFrequency(SetFrequencyCommand { frequency: 5386 }),
+            Command::SetFrequency(SetFrequencyCommand { frequency: 5792 }),
+        ];
+        for command in commands {
+            len += command.to_bytes(&mut frames[len..]).unwrap();
+        }
+        len += RawFrameBuilder::new(command::SET_MODE, &[0x90])
+            .build(&mut frames[len..])
+            .unwrap();
+
+        let mut sniffer = Sniffer::default();
+        let mut exchanges = sniff(&mut sniffer, &frames[..len], 0);
+        exchanges.extend(sniffer.flush());
+        let expected: Vec<_> = commands
+            .into_iter()
+            .chain([Command::SetMode(SetModeCommand {
+                pitmode_in_range_active: false,
+                pitmode_out_range_active: false,
+                pitmode_enabled: false,
+                unlocked: false,
+            })])
+            .map(|command| Exchange::Unanswered {
+                command,
+                sent_at: 0,
+            })
+            .collect();
+        assert_eq!(exchanges, expected);
+    }
+
+    #[test]
+    fn test_unsolicited_and_corrupted() {
+        let mut sniffer = Sniffer::default();
+        let exchanges = sniff(&mut sniffer, &SETTINGS, 0);
+        assert!(matches!(
+            exchanges[..],
+            [Exchange::Unsolicited { at: 0, .. }]
+        ));
+
+        let mut corrupted = SETTINGS;
+        corrupted[9] ^= 0xFF;
+        // A settings reply by its command byte and length, no need to wait
+        // for a host frame one byte longer.
+        let exchanges = sniff(&mut sniffer, &corrupted, 5);
+        assert!(matches!(
+            exchanges[..],
+            [Exchange::Error {
+                error: SmartAudioError::InvalidCrc { .. },
+                at: 5
+            }]
+        ));
+        assert_eq!(sniffer.flush(), None);
+    }
+}
@@ -0,0 +1,349 @@
+use crate::timing::BITS_PER_BYTE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SoftUartConfig {
+    pub baud: u32,
+    /// Frequency of the clock the edge timestamps are taken from.
+    pub tick_hz: u32,
+    /// Line idles low and the start bit is high.
+    pub inverted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SoftUartError {
+    /// One of the two stop bits was not at idle level, carries the data bits.
+    Framing(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Receiving {
+    start: u32,
+    bit: u32,
+    bits: u16,
+}
+
+/// Software UART receiver decoding 8N2 bytes from line edges.
+///
+/// Edges are captured by a timer as `(level, timestamp)` pairs. Each start
+/// bit resynchronises the bit clock and bits are sampled in their centre,
+/// which tolerates a few percent of skew between the VTX and the host.
+#[derive(Debug, Clone)]
+pub struct SoftUartRx {
+    config: SoftUartConfig,
+    /// Logical line level, `true` is idle.
+    level: bool,
+    receiving: Option<Receiving>,
+}
+
+impl SoftUartRx {
+    pub fn new(config: SoftUartConfig) -> Self {
+        Self {
+            config,
+            level: true,
+            receiving: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.level = true;
+        self.receiving = None;
+    }
+
+    /// Timestamp of the start edge of the byte being received, if any.
+    pub fn receiving_since(&self) -> Option<u32> {
+        self.receiving.map(|rx| rx.start)
+    }
+
+    /// Timestamp offset of the centre of bit `bit` from the start edge.
+    fn bit_center(&self, bit: u32) -> u32 {
+        let offset = u64::from(2 * bit + 1) * u64::from(self.config.tick_hz)
+            / (2 * u64::from(self.config.baud.max(1)));
+        offset as u32
+    }
+
+    /// Samples every bit centred before `now` at the current line level.
+    fn sample_until(&mut self, now: u32) -> Option<Result<u8, SoftUartError>> {
+        let mut rx = self.receiving?;
+        while rx.bit < BITS_PER_BYTE && now.wrapping_sub(rx.start) > self.bit_center(rx.bit) {
+            if rx.bit == 0 && self.level {
+                // Start bit did not last until its centre, it was a glitch.
+                self.receiving = None;
+                return None;
+            }
+            rx.bits |= u16::from(self.level) << rx.bit;
+            rx.bit += 1;
+        }
+        if rx.bit < BITS_PER_BYTE {
+            self.receiving = Some(rx);
+            return None;
+        }
+
+        self.receiving = None;
+        let byte = (rx.bits >> 1) as u8;
+        let stop_bits = rx.bits >> 9;
+        if stop_bits == 0b11 {
+            Some(Ok(byte))
+        } else {
+            Some(Err(SoftUartError::Framing(byte)))
+        }
+    }
+
+    /// Feeds a line edge, `level` is the raw pin level after the edge.
+    pub fn push_edge(&mut self, level: bool, timestamp: u32) -> Option<Result<u8, SoftUartError>> {
+        let result = self.sample_until(timestamp);
+        self.level = level != self.config.inverted;
+        if self.receiving.is_none() && !self.level {
+            self.receiving = Some(Receiving {
+                start: timestamp,
+                bit: 0,
+                bits: 0,
+            });
+        }
+        result
+    }
+
+    /// Completes a byte whose last bits ended without an edge, call it
+    /// periodically or once the line has been idle for a byte time.
+    pub fn poll(&mut self, now: u32) -> Option<Result<u8, SoftUartError>> {
+        self.sample_until(now)
+    }
+}
+
+/// Constant line level held for `ticks` timer ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Segment {
+    /// Raw pin level, already inverted when the config asks for it.
+    pub level: bool,
+    pub ticks: u32,
+}
+
+/// Bit-banged 8N2 waveform of encoded bytes, e.g. from
+/// [`crate::SmartAudioCommand::to_bytes`].
+///
+/// Consecutive bits with the same level are merged into one [`Segment`].
+/// Segment boundaries are computed from the exact bit period, so rounding
+/// does not accumulate over the frame.
+#[derive(Debug, Clone)]
+pub struct Waveform<'a> {
+    config: SoftUartConfig,
+    bytes: &'a [u8],
+    bit: usize,
+}
+
+impl<'a> Waveform<'a> {
+    pub fn new(bytes: &'a [u8], config: SoftUartConfig) -> Self {
+        Self {
+            config,
+            bytes,
+            bit: 0,
+        }
+    }
+
+    fn total_bits(&self) -> usize {
+        self.bytes.len() * BITS_PER_BYTE as usize
+    }
+
+    /// Logical level of bit `bit` in the frame, `true` is idle.
+    fn level(&self, bit: usize) -> bool {
+        let byte = self.bytes[bit / BITS_PER_BYTE as usize];
+        match bit % BITS_PER_BYTE as usize {
+            0 => false,
+            i @ 1..=8 => byte & (1 << (i - 1)) != 0,
+            _ => true,
+        }
+    }
+
+    /// Ticks from the start of the frame to the start of bit `bit`.
+    fn boundary(&self, bit: usize) -> u32 {
+        (bit as u64 * u64::from(self.config.tick_hz) / u64::from(self.config.baud.max(1))) as u32
+    }
+
+    /// Total duration of the waveform in ticks.
+    pub fn duration(&self) -> u32 {
+        self.boundary(self.total_bits())
+    }
+
+    /// Timer compare values at which the line has to toggle, counted from
+    /// the start bit of the first byte. The last value marks the end of the
+    /// final stop bit, when the line may be released.
+    pub fn compare_values(self) -> impl Iterator<Item = u32> + 'a {
+        let mut at = 0u32;
+        self.map(move |segment| {
+            at = at.wrapping_add(segment.ticks);
+            at
+        })
+    }
+}
+
+impl Iterator for Waveform<'_> {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let total = self.total_bits();
+        if self.bit >= total {
+            return None;
+        }
+        let start = self.bit;
+        let level = self.level(start);
+        while self.bit < total && self.level(self.bit) == level {
+            self.bit += 1;
+        }
+        Some(Segment {
+            level: level != self.config.inverted,
+            ticks: self.boundary(self.bit) - self.boundary(start),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use std::vec::Vec;
+
+    const TICK_HZ: u32 = 1_000_000;
+
+    /// Renders bytes to edges with the given bit time in ticks.
+    fn edges(bytes: &[u8], bit_ticks: f64, inverted: bool) -> Vec<(bool, u32)> {
+        let mut edges = Vec::new();
+        let mut level = true;
+        let mut t = 1000.0;
+        for byte in bytes {
+            let mut bits = [true; 11];
+            bits[0] = false;
+            for (i, bit) in bits.iter_mut().enumerate().skip(1).take(8) {
+                *bit = byte & (1 << (i - 1)) != 0;
+            }
+            for bit in bits {
+                if bit != level {
+                    level = bit;
+                    edges.push((level != inverted, t as u32));
+                }
+                t += bit_ticks;
+            }
+        }
+        edges
+    }
+
+    fn decode(rx: &mut SoftUartRx, edges: &[(bool, u32)]) -> Vec<Result<u8, SoftUartError>> {
+        let mut out: Vec<_> = edges
+            .iter()
+            .filter_map(|(level, t)| rx.push_edge(*level, *t))
+            .collect();
+        out.extend(rx.poll(u32::MAX / 2));
+        out
+    }
+
+    fn config(inverted: bool) -> SoftUartConfig {
+        SoftUartConfig {
+            baud: 4800,
+            tick_hz: TICK_HZ,
+            inverted,
+        }
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        let bytes = [0xAA, 0x55, 0x09, 0x06, 0x01, 0x00, 0x1A, 0x16, 0xE9, 0x0A];
+        let mut rx = SoftUartRx::new(config(false));
+        let decoded = decode(&mut rx, &edges(&bytes, 208.333, false));
+        assert_eq!(decoded, bytes.map(Ok));
+    }
+
+    #[test]
+    fn test_decode_inverted_with_skew() {
+        let bytes = [0xAA, 0x55, 0x03, 0x00, 0x9F, 0xFF, 0x00];
+        for skew in [0.96, 1.04] {
+            let mut rx = SoftUartRx::new(config(true));
+            let decoded = decode(&mut rx, &edges(&bytes, 208.333 * skew, true));
+            assert_eq!(decoded, bytes.map(Ok));
+        }
+    }
+
+    #[test]
+    fn test_framing_error() {
+        let mut rx = SoftUartRx::new(config(false));
+        // Start bit followed by a line held low for a whole frame.
+        assert_eq!(rx.push_edge(false, 0), None);
+        assert_eq!(rx.poll(5000), Some(Err(SoftUartError::Framing(0x00))));
+    }
+
+    #[test]
+    fn test_waveform_segments() {
+        let config = SoftUartConfig {
+            baud: 4800,
+            tick_hz: 48_000,
+            inverted: false,
+        };
+        // 0xAA is 0,0101_0101,11 on the wire, every bit is 10 ticks.
+        let segments: Vec<_> = Waveform::new(&[0xAA], config).collect();
+        let levels: Vec<_> = segments.iter().map(|s| (s.level, s.ticks)).collect();
+        assert_eq!(
+            levels,
+            [
+                (false, 20),
+                (true, 10),
+                (false, 10),
+                (true, 10),
+                (false, 10),
+                (true, 10),
+                (false, 10),
+                (true, 30),
+            ]
+        );
+        let inverted = SoftUartConfig {
+            inverted: true,
+            ..config
+        };
+        let first = Waveform::new(&[0xAA], inverted).next().unwrap();
+        assert_eq!(
+            first,
+            Segment {
+                level: true,
+                ticks: 20
+            }
+        );
+        assert_eq!(Waveform::new(&[0xAA, 0x55], config).duration(), 220);
+    }
+
+    #[test]
+    fn test_waveform_round_trip() {
+        let mut buffer = [0; 8];
+        let size = crate::commands::SmartAudioCommand::to_bytes(
+            &crate::commands::SetFrequencyCommand { frequency: 5865 },
+            &mut buffer,
+        )
+        .unwrap();
+        let bytes = &buffer[..size];
+        for inverted in [false, true] {
+            let config = config(inverted);
+            let mut level = !inverted;
+            let mut edges = Vec::new();
+            let mut at = 1000;
+            for segment in Waveform::new(bytes, config) {
+                if segment.level != level {
+                    edges.push((segment.level, at));
+                    level = segment.level;
+                }
+                at += segment.ticks;
+            }
+            let last = Waveform::new(bytes, config).compare_values().last();
+            assert_eq!(last, Some(at - 1000));
+
+            let mut rx = SoftUartRx::new(config);
+            let decoded = decode(&mut rx, &edges);
+            assert_eq!(decoded, bytes.iter().map(|b| Ok(*b)).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_glitch_is_ignored() {
+        let mut rx = SoftUartRx::new(config(false));
+        assert_eq!(rx.push_edge(false, 0), None);
+        assert_eq!(rx.push_edge(true, 20), None);
+        assert_eq!(rx.poll(5000), None);
+    }
+}
@@ -0,0 +1,336 @@
+use crate::responses::PowerSettings;
+use crate::responses::Response;
+use crate::responses::Settings;
+use crate::responses::Version;
+
+/// Value reported by the VTX together with the time it was observed.
+///
+/// Timestamps are host supplied milliseconds and may wrap around.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Observed<T> {
+    pub value: T,
+    pub updated_at: u32,
+}
+
+impl<T> Observed<T> {
+    pub fn new(value: T, updated_at: u32) -> Self {
+        Self { value, updated_at }
+    }
+
+    pub fn age(&self, now: u32) -> u32 {
+        now.wrapping_sub(self.updated_at)
+    }
+
+    pub fn is_stale(&self, now: u32, max_age: u32) -> bool {
+        self.age(now) > max_age
+    }
+}
+
+/// Best known view of the VTX state, folded from every parsed response.
+///
+/// `GetSettings` refreshes every field, while `Set*` responses only refresh
+/// the fields they acknowledge. Fields never reported stay `None`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct VtxState {
+    pub version: Option<Observed<Version>>,
+    pub channel: Option<Observed<u8>>,
+    pub power_level: Option<Observed<u8>>,
+    pub power_dbm: Option<Observed<u8>>,
+    pub frequency: Option<Observed<u16>>,
+    pub unlocked: Option<Observed<bool>>,
+    pub user_frequency_mode: Option<Observed<bool>>,
+    pub pitmode_enabled: Option<Observed<bool>>,
+    pub pitmode_in_range_active: Option<Observed<bool>>,
+    pub pitmode_out_range_active: Option<Observed<bool>>,
+    pub power_settings: Option<Observed<PowerSettings>>,
+}
+
+impl VtxState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Folds a response received at `now` into the cached state.
+    pub fn apply(&mut self, response: &Response, now: u32) {
+        match response {
+            Response::GetSettings(settings) => self.apply_settings(settings, now),
+            Response::SetPower(r) => {
+                // SmartAudio 2.1 acknowledges power in dBm, older versions echo the level index.
+                if self.version() == Some(Version::V2_1) {
+                    self.power_dbm = Some(Observed::new(r.power, now));
+                    // The level is the index of the dBm value in the power table.
+                    self.power_level = self
+                        .power_settings
+                        .and_then(|p| {
+                            let table = p.value;
+                            [
+                                table.dbm_level_1,
+                                table.dbm_level_2,
+                                table.dbm_level_3,
+                                table.dbm_level_4,
+                            ]
+                            .iter()
+                            .position(|dbm| *dbm == r.power)
+                        })
+                        .map(|level| Observed::new(level as u8, now));
+                } else {
+                    self.power_level = Some(Observed::new(r.power, now));
+                }
+            }
+            Response::SetChannel(r) => {
+                // The frequency of the channel is only known from the next settings.
+                self.channel = Some(Observed::new(r.channel, now));
+                self.frequency = None;
+                self.user_frequency_mode = Some(Observed::new(false, now));
+            }
+            Response::SetFrequency(r) => {
+                self.frequency = Some(Observed::new(r.frequency, now));
+                self.user_frequency_mode = Some(Observed::new(true, now));
+            }
+            Response::SetMode(r) => {
+                self.pitmode_enabled = Some(Observed::new(r.pitmode_enabled, now));
+                self.pitmode_in_range_active = Some(Observed::new(r.pitmode_in_range_active, now));
+                self.pitmode_out_range_active =
+                    Some(Observed::new(r.pitmode_out_range_active, now));
+                self.unlocked = Some(Observed::new(r.unlocked, now));
+            }
+            Response::Unknown(_) => (),
+        }
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings, now: u32) {
+        self.version = Some(Observed::new(settings.version, now));
+        self.channel = Some(Observed::new(settings.channel, now));
+        self.power_level = Some(Observed::new(settings.power_level, now));
+        self.frequency = Some(Observed::new(settings.frequency, now));
+        self.unlocked = Some(Observed::new(settings.unlocked, now));
+        self.user_frequency_mode = Some(Observed::new(settings.user_frequency_mode, now));
+        self.pitmode_enabled = Some(Observed::new(settings.pitmode_enabled, now));
+        self.pitmode_in_range_active = Some(Observed::new(settings.pitmode_in_range_active, now));
+        self.pitmode_out_range_active = Some(Observed::new(settings.pitmode_out_range_active, now));
+        match settings.power_settings {
+            Some(power_settings) => {
+                self.power_dbm = Some(Observed::new(power_settings.current_power, now));
+                self.power_settings = Some(Observed::new(power_settings, now));
+            }
+            None => {
+                self.power_dbm = None;
+                self.power_settings = None;
+            }
+        }
+    }
+
+    pub fn version(&self) -> Option<Version> {
+        self.version.map(|v| v.value)
+    }
+
+    /// Time of the oldest field refresh, `None` when nothing is known yet.
+    pub fn oldest_update(&self, now: u32) -> Option<u32> {
+        self.timestamps().max_by_key(|t| now.wrapping_sub(*t))
+    }
+
+    /// Time of the most recent field refresh, `None` when nothing is known yet.
+    pub fn last_update(&self, now: u32) -> Option<u32> {
+        self.timestamps().min_by_key(|t| now.wrapping_sub(*t))
+    }
+
+    /// Returns `true` if any field required for a full [`Settings`] view is
+    /// missing or older than `max_age`.
+    pub fn is_stale(&self, now: u32, max_age: u32) -> bool {
+        self.required_timestamps()
+            .into_iter()
+            .any(|t| t.is_none_or(|t| now.wrapping_sub(t) > max_age))
+    }
+
+    /// Rebuilds a [`Settings`] view, `None` until every field was reported at least once.
+    pub fn settings(&self) -> Option<Settings> {
+        let version = self.version?.value;
+        let power_settings = match version {
+            Version::V2_1 => {
+                let mut power_settings = self.power_settings?.value;
+                if let Some(power_dbm) = self.power_dbm {
+                    power_settings.current_power = power_dbm.value;
+                }
+                Some(power_settings)
+            }
+            _ => None,
+        };
+        Some(Settings {
+            version,
+            channel: self.channel?.value,
+            power_level: self.power_level?.value,
+            frequency: self.frequency?.value,
+            unlocked: self.unlocked?.value,
+            user_frequency_mode: self.user_frequency_mode?.value,
+            pitmode_enabled: self.pitmode_enabled?.value,
+            pitmode_in_range_active: self.pitmode_in_range_active?.value,
+            pitmode_out_range_active: self.pitmode_out_range_active?.value,
+            power_settings,
+        })
+    }
+
+    fn required_timestamps(&self) -> [Option<u32>; 9] {
+        [
+            self.version.map(|v| v.updated_at),
+            self.channel.map(|v| v.updated_at),
+            self.power_level.map(|v| v.updated_at),
+            self.frequency.map(|v| v.updated_at),
+            self.unlocked.map(|v| v.updated_at),
+            self.user_frequency_mode.map(|v| v.updated_at),
+            self.pitmode_enabled.map(|v| v.updated_at),
+            self.pitmode_in_range_active.map(|v| v.updated_at),
+            self.pitmode_out_range_active.map(|v| v.updated_at),
+        ]
+    }
+
+    fn timestamps(&self) -> impl Iterator<Item = u32> {
+        self.required_timestamps()
+            .into_iter()
+            .chain([
+                self.power_dbm.map(|v| v.updated_at),
+                self.power_settings.map(|v| v.updated_at),
+            ])
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::responses::SetChannelResponse;
+    use crate::responses::SetFrequencyResponse;
+    use crate::responses::SetModeResponse;
+    use crate::responses::SetPowerResponse;
+
+    fn settings_v21() -> Settings {
+        Settings {
+            version: Version::V2_1,
+            channel: 0,
+            power_level: 0,
+            frequency: 5865,
+            unlocked: false,
+            user_frequency_mode: false,
+            pitmode_enabled: false,
+            pitmode_in_range_active: false,
+            pitmode_out_range_active: false,
+            power_settings: Some(PowerSettings {
+                current_power: 14,
+                num_power_levels: 3,
+                dbm_level_1: 0,
+                dbm_level_2: 14,
+                dbm_level_3: 20,
+                dbm_level_4: 26,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_empty_state_is_stale() {
+        let state = VtxState::new();
+        assert!(state.is_stale(0, 1000));
+        assert_eq!(state.settings(), None);
+        assert_eq!(state.last_update(0), None);
+    }
+
+    #[test]
+    fn test_settings_fill_state() {
+        let mut state = VtxState::new();
+        state.apply(&Response::GetSettings(settings_v21()), 100);
+        assert_eq!(state.settings(), Some(settings_v21()));
+        assert!(!state.is_stale(600, 1000));
+        assert!(state.is_stale(1101, 1000));
+    }
+
+    #[test]
+    fn test_partial_responses_refresh_fields() {
+        let mut state = VtxState::new();
+        state.apply(&Response::GetSettings(settings_v21()), 100);
+        state.apply(
+            &Response::SetChannel(SetChannelResponse { channel: 32 }),
+            200,
+        );
+        state.apply(&Response::SetPower(SetPowerResponse { power: 20 }), 300);
+        state.apply(
+            &Response::SetMode(SetModeResponse {
+                pitmode_in_range_active: false,
+                pitmode_out_range_active: true,
+                pitmode_enabled: true,
+                unlocked: true,
+            }),
+            400,
+        );
+
+        state.apply(
+            &Response::SetFrequency(SetFrequencyResponse { frequency: 5658 }),
+            400,
+        );
+
+        let settings = state.settings().unwrap();
+        assert_eq!(settings.channel, 32);
+        assert_eq!(settings.frequency, 5658);
+        assert_eq!(settings.power_settings.unwrap().current_power, 20);
+        assert!(settings.pitmode_enabled);
+        assert!(settings.pitmode_out_range_active);
+        assert!(settings.unlocked);
+        assert_eq!(state.channel.unwrap().age(250), 50);
+        assert_eq!(state.oldest_update(400), Some(100));
+        assert_eq!(state.last_update(400), Some(400));
+    }
+
+    #[test]
+    fn test_channel_and_frequency_acks_switch_mode() {
+        let mut state = VtxState::new();
+        state.apply(&Response::GetSettings(settings_v21()), 100);
+        state.apply(
+            &Response::SetFrequency(SetFrequencyResponse { frequency: 5658 }),
+            200,
+        );
+        assert_eq!(state.frequency.unwrap().value, 5658);
+        assert!(state.user_frequency_mode.unwrap().value);
+
+        state.apply(
+            &Response::SetChannel(SetChannelResponse { channel: 32 }),
+            300,
+        );
+        assert_eq!(state.frequency, None);
+        assert!(!state.user_frequency_mode.unwrap().value);
+        assert_eq!(state.settings(), None);
+        assert!(state.is_stale(300, 1000));
+    }
+
+    #[test]
+    fn test_set_power_v21_maps_level() {
+        let mut state = VtxState::new();
+        state.apply(&Response::GetSettings(settings_v21()), 100);
+        state.apply(&Response::SetPower(SetPowerResponse { power: 26 }), 200);
+        assert_eq!(state.power_dbm.unwrap().value, 26);
+        assert_eq!(state.power_level, Some(Observed::new(3, 200)));
+
+        // Not in the power table, the level is unknown.
+        state.apply(&Response::SetPower(SetPowerResponse { power: 17 }), 300);
+        assert_eq!(state.power_dbm.unwrap().value, 17);
+        assert_eq!(state.power_level, None);
+    }
+
+    #[test]
+    fn test_set_power_before_v21_updates_level() {
+        let mut state = VtxState::new();
+        state.apply(&Response::SetPower(SetPowerResponse { power: 2 }), 10);
+        assert_eq!(state.power_level.unwrap().value, 2);
+        assert_eq!(state.power_dbm, None);
+    }
+
+    #[test]
+    fn test_staleness_with_wrapping_clock() {
+        let mut state = VtxState::new();
+        state.apply(&Response::GetSettings(settings_v21()), u32::MAX - 10);
+        assert!(!state.is_stale(20, 100));
+        assert!(state.is_stale(200, 100));
+    }
+}
@@ -0,0 +1,45 @@
+/// Bits on the wire per byte: start bit, 8 data bits and 2 stop bits (8N2).
+pub const BITS_PER_BYTE: u32 = 11;
+
+/// Time needed to shift out `bytes` bytes at `baud`, rounded up to the next
+/// microsecond so the line is never considered idle too early.
+pub const fn wire_time_us(baud: u32, bytes: usize) -> u32 {
+    if baud == 0 {
+        return 0;
+    }
+    let bits = bytes as u64 * BITS_PER_BYTE as u64;
+    let us = (bits * 1_000_000).div_ceil(baud as u64);
+    if us > u32::MAX as u64 {
+        u32::MAX
+    } else {
+        us as u32
+    }
+}
+
+/// Duration of a single bit at `baud`, in ticks of a `tick_hz` clock.
+pub const fn bit_time_ticks(baud: u32, tick_hz: u32) -> u32 {
+    if baud == 0 {
+        return 0;
+    }
+    tick_hz / baud
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wire_time() {
+        // 11 bits at 4800 baud is 2291.67us per byte.
+        assert_eq!(wire_time_us(4800, 1), 2292);
+        assert_eq!(wire_time_us(4800, 6), 13750);
+        assert_eq!(wire_time_us(4800, 0), 0);
+        assert_eq!(wire_time_us(0, 6), 0);
+    }
+
+    #[test]
+    fn test_bit_time() {
+        assert_eq!(bit_time_ticks(4800, 48_000_000), 10_000);
+        assert_eq!(bit_time_ticks(0, 48_000_000), 0);
+    }
+}
@@ -0,0 +1,218 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::OutputPin;
+
+use crate::commands::SmartAudioCommand;
+use crate::constants::MAX_FRAME_SIZE;
+use crate::parser::SmartAudioError;
+use crate::timing::wire_time_us;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransmitError<P, W> {
+    Encode(SmartAudioError),
+    Pin(P),
+    Write(W),
+}
+
+/// Drives a transmit-enable (DE) pin around each command on half-duplex
+/// transceivers.
+///
+/// The pin is set high before the frame is written and released once the
+/// last stop bit left the wire, so the VTX reply is not cut off. The release
+/// is timed from the return of the write, which must therefore be
+/// non-blocking: a write that waits for the bytes to be shifted out would
+/// keep the pin high for about one more frame.
+#[derive(Debug)]
+pub struct DirectionControl<P> {
+    pin: P,
+    baud: u32,
+}
+
+impl<P: OutputPin> DirectionControl<P> {
+    pub fn new(pin: P, baud: u32) -> Self {
+        Self { pin, baud }
+    }
+
+    pub fn baud(&self) -> u32 {
+        self.baud
+    }
+
+    /// Changes the baud rate used for wire time, e.g. after a [`crate::BaudSearch`] update.
+    pub fn set_baud(&mut self, baud: u32) {
+        self.baud = baud;
+    }
+
+    /// Releases the line so the VTX can reply.
+    pub fn release(&mut self) -> Result<(), P::Error> {
+        self.pin.set_low()
+    }
+
+    pub fn free(self) -> P {
+        self.pin
+    }
+
+    /// Encodes and sends `command`, returning the number of bytes written.
+    ///
+    /// `write` must hand the bytes to the UART and return before they are
+    /// shifted out (FIFO, DMA or interrupt driven TX). The line is then held
+    /// for the full wire time of the frame.
+    pub fn send<C, D, W, E>(
+        &mut self,
+        command: &C,
+        delay: &mut D,
+        write: W,
+    ) -> Result<usize, TransmitError<P::Error, E>>
+    where
+        C: SmartAudioCommand,
+        D: DelayNs,
+        W: FnOnce(&[u8]) -> Result<(), E>,
+    {
+        let mut buffer = [0; MAX_FRAME_SIZE];
+        let size = command
+            .to_bytes(&mut buffer)
+            .map_err(TransmitError::Encode)?;
+        self.send_bytes(&buffer[..size], delay, write)?;
+        Ok(size)
+    }
+
+    /// Sends already encoded bytes, see [`Self::send`].
+    ///
+    /// `write` must return as soon as the bytes are queued for the UART, not
+    /// once they are transmitted, as the pin is held for the full wire time
+    /// of `bytes` after it returns. With a blocking `write`, such as an
+    /// `embedded_io::Write` followed by `flush`, use [`Self::send_blocking`].
+    pub fn send_bytes<D, W, E>(
+        &mut self,
+        bytes: &[u8],
+        delay: &mut D,
+        write: W,
+    ) -> Result<(), TransmitError<P::Error, E>>
+    where
+        D: DelayNs,
+        W: FnOnce(&[u8]) -> Result<(), E>,
+    {
+        self.pin.set_high().map_err(TransmitError::Pin)?;
+        let written = write(bytes);
+        if written.is_ok() {
+            delay.delay_us(wire_time_us(self.baud, bytes.len()));
+        }
+        self.release().map_err(TransmitError::Pin)?;
+        written.map_err(TransmitError::Write)
+    }
+
+    /// Sends already encoded bytes with a `write` that returns only once the
+    /// last stop bit left the wire, e.g. a write followed by a flush waiting
+    /// for the UART transmission complete flag. The pin is released right
+    /// after it, without a delay.
+    pub fn send_blocking<W, E>(
+        &mut self,
+        bytes: &[u8],
+        write: W,
+    ) -> Result<(), TransmitError<P::Error, E>>
+    where
+        W: FnOnce(&[u8]) -> Result<(), E>,
+    {
+        self.pin.set_high().map_err(TransmitError::Pin)?;
+        let written = write(bytes);
+        self.release().map_err(TransmitError::Pin)?;
+        written.map_err(TransmitError::Write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::commands::GetSettingsCommand;
+    use core::convert::Infallible;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::vec::Vec;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Event {
+        High,
+        Low,
+        Write(usize),
+        Delay(u32),
+    }
+
+    type Log = Rc<RefCell<Vec<Event>>>;
+
+    struct Pin(Log);
+
+    impl embedded_hal::digital::ErrorType for Pin {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Pin {
+        fn set_low(&mut self) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push(Event::Low);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Self::Error> {
+            self.0.borrow_mut().push(Event::High);
+            Ok(())
+        }
+    }
+
+    struct Delay(Log);
+
+    impl DelayNs for Delay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0.borrow_mut().push(Event::Delay(ns / 1000));
+        }
+
+        fn delay_us(&mut self, us: u32) {
+            self.0.borrow_mut().push(Event::Delay(us));
+        }
+    }
+
+    #[test]
+    fn test_pin_wraps_frame() {
+        let log = Log::default();
+        let mut control = DirectionControl::new(Pin(log.clone()), 4800);
+        let mut delay = Delay(log.clone());
+        let size = control
+            .send(&GetSettingsCommand {}, &mut delay, |bytes| {
+                log.borrow_mut().push(Event::Write(bytes.len()));
+                Ok::<(), Infallible>(())
+            })
+            .unwrap();
+        assert_eq!(size, 5);
+        assert_eq!(
+            *log.borrow(),
+            [
+                Event::High,
+                Event::Write(5),
+                Event::Delay(11459),
+                Event::Low
+            ]
+        );
+    }
+
+    #[test]
+    fn test_blocking_write_is_not_delayed() {
+        let log = Log::default();
+        let mut control = DirectionControl::new(Pin(log.clone()), 4800);
+        let frame = GetSettingsCommand {}.to_array();
+        control
+            .send_blocking(&frame, |bytes| {
+                log.borrow_mut().push(Event::Write(bytes.len()));
+                Ok::<(), Infallible>(())
+            })
+            .unwrap();
+        assert_eq!(*log.borrow(), [Event::High, Event::Write(5), Event::Low]);
+    }
+
+    #[test]
+    fn test_pin_released_on_write_error() {
+        let log = Log::default();
+        let mut control = DirectionControl::new(Pin(log.clone()), 4800);
+        let mut delay = Delay(log.clone());
+        let result = control.send_bytes(&[0xAA], &mut delay, |_| Err(()));
+        assert_eq!(result, Err(TransmitError::Write(())));
+        assert_eq!(*log.borrow(), [Event::High, Event::Low]);
+    }
+}
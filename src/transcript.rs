@@ -0,0 +1,392 @@
+use core::fmt;
+use core::fmt::Write;
+
+use crate::commands::Command;
+use crate::commands::Power;
+use crate::parser::SmartAudioError;
+use crate::responses::Response;
+use crate::responses::Version;
+use crate::sniffer::Exchange;
+
+const BANDS: [char; 5] = ['A', 'B', 'E', 'F', 'R'];
+
+/// Band letter and channel number (1 to 8) of a 40 channel table index,
+/// bands in the usual A, B, E, F, R order.
+pub const fn channel_name(index: u8) -> Option<(char, u8)> {
+    if index as usize >= BANDS.len() * 8 {
+        return None;
+    }
+    Some((BANDS[index as usize / 8], index % 8 + 1))
+}
+
+struct Channel(u8);
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match channel_name(self.0) {
+            Some((band, number)) => write!(f, "{band}{number} (idx {})", self.0),
+            None => write!(f, "idx {}", self.0),
+        }
+    }
+}
+
+struct Flags {
+    pitmode_in_range_active: bool,
+    pitmode_out_range_active: bool,
+    pitmode_enabled: bool,
+    unlocked: bool,
+}
+
+impl fmt::Display for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.pitmode_in_range_active, "pit in-range"),
+            (self.pitmode_out_range_active, "pit out-range"),
+            (self.pitmode_enabled, "pit enabled"),
+            (self.unlocked, "unlocked"),
+        ];
+        let mut first = true;
+        for (_, name) in flags.iter().filter(|(set, _)| *set) {
+            if !first {
+                f.write_str(", ")?;
+            }
+            f.write_str(name)?;
+            first = false;
+        }
+        if first {
+            f.write_str("no flags")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::V1_0 => "V1.0",
+            Self::V2_0 => "V2.0",
+            Self::V2_1 => "V2.1",
+            Self::Unknown => "unknown version",
+        })
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GetSettings(_) => write!(f, "GET_SETTINGS"),
+            Self::SetPower(command) => match command.power {
+                Power::Level(level) => write!(f, "SET_POWER level {level}"),
+                Power::dBm(dbm) => write!(f, "SET_POWER {dbm} dBm"),
+            },
+            Self::SetChannel(command) => write!(f, "SET_CHANNEL {}", Channel(command.channel)),
+            Self::SetFrequency(command) => write!(f, "SET_FREQUENCY {} MHz", command.frequency),
+            Self::SetMode(command) => write!(
+                f,
+                "SET_MODE {}",
+                Flags {
+                    pitmode_in_range_active: command.pitmode_in_range_active,
+                    pitmode_out_range_active: command.pitmode_out_range_active,
+                    pitmode_enabled: command.pitmode_enabled,
+                    unlocked: command.unlocked,
+                }
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GetSettings(settings) => {
+                write!(
+                    f,
+                    "settings {} channel {} {} MHz power {}",
+                    settings.version,
+                    Channel(settings.channel),
+                    settings.frequency,
+                    settings.power_level
+                )?;
+                if let Some(power) = settings.power_settings {
+                    write!(f, " ({} dBm)", power.current_power)?;
+                }
+                if settings.pitmode_enabled {
+                    f.write_str(", pit mode")?;
+                }
+                if settings.user_frequency_mode {
+                    f.write_str(", user frequency")?;
+                }
+                if !settings.unlocked {
+                    f.write_str(", locked")?;
+                }
+                Ok(())
+            }
+            Self::SetPower(response) => write!(f, "ack power {}", response.power),
+            Self::SetChannel(response) => write!(f, "ack channel {}", response.channel),
+            Self::SetFrequency(response) => {
+                write!(f, "ack frequency {} MHz", response.frequency)
+            }
+            Self::SetMode(response) => write!(
+                f,
+                "ack mode {}",
+                Flags {
+                    pitmode_in_range_active: response.pitmode_in_range_active,
+                    pitmode_out_range_active: response.pitmode_out_range_active,
+                    pitmode_enabled: response.pitmode_enabled,
+                    unlocked: response.unlocked,
+                }
+            ),
+            Self::Unknown(command) => write!(f, "unknown reply 0x{command:02X}"),
+        }
+    }
+}
+
+/// Renders sniffed [`Exchange`]s as one line each, e.g.
+/// `    1200 ms → SET_CHANNEL R1 (idx 32) … ← ack channel 32 after 87 ms`.
+///
+/// The protocol version is taken from settings replies and reported when
+/// first seen or when it changes. Replies that do not confirm the requested
+/// value, and commands the detected version does not support, get a
+/// warning line below the exchange.
+#[derive(Debug, Default, Clone)]
+pub struct Transcript {
+    version: Option<Version>,
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Version from the last settings reply.
+    pub fn version(&self) -> Option<Version> {
+        self.version
+    }
+
+    /// Writes every exchange of `exchanges`.
+    pub fn write_all<W: Write>(
+        &mut self,
+        out: &mut W,
+        exchanges: impl IntoIterator<Item = Exchange>,
+    ) -> fmt::Result {
+        exchanges
+            .into_iter()
+            .try_for_each(|exchange| self.write(out, &exchange))
+    }
+
+    pub fn write<W: Write>(&mut self, out: &mut W, exchange: &Exchange) -> fmt::Result {
+        match *exchange {
+            Exchange::Transaction {
+                command,
+                response,
+                sent_at,
+                replied_at,
+            } => {
+                writeln!(
+                    out,
+                    "{sent_at:>8} ms → {command} … ← {response} after {} ms",
+                    replied_at.wrapping_sub(sent_at)
+                )?;
+                self.detect_version(out, &response)?;
+                self.check_support(out, &command)?;
+                check_reply(out, &command, &response)
+            }
+            Exchange::Unanswered { command, sent_at } => {
+                writeln!(out, "{sent_at:>8} ms → {command} … no reply")?;
+                self.check_support(out, &command)
+            }
+            Exchange::Unsolicited { response, at } => {
+                writeln!(out, "{at:>8} ms ← {response} (unsolicited)")?;
+                self.detect_version(out, &response)
+            }
+            Exchange::Error {
+                error:
+                    SmartAudioError::InvalidCrc {
+                        calculated_crc,
+                        frame_crc,
+                    },
+                at,
+            } => writeln!(
+                out,
+                "{at:>8} ms ✗ CRC error: frame 0x{frame_crc:02X}, calculated 0x{calculated_crc:02X}"
+            ),
+            Exchange::Error { error, at } => writeln!(out, "{at:>8} ms ✗ {error:?}"),
+        }
+    }
+
+    fn detect_version<W: Write>(&mut self, out: &mut W, response: &Response) -> fmt::Result {
+        let Response::GetSettings(settings) = response else {
+            return Ok(());
+        };
+        let version = settings.version;
+        match self.version.replace(version) {
+            None => writeln!(out, "            VTX speaks SmartAudio {version}"),
+            Some(previous) if previous != version => writeln!(
+                out,
+                "            ! VTX version changed from {previous} to {version}"
+            ),
+            Some(_) => Ok(()),
+        }
+    }
+
+    fn check_support<W: Write>(&self, out: &mut W, command: &Command) -> fmt::Result {
+        match (command, self.version) {
+            (Command::SetPower(command), Some(version))
+                if matches!(command.power, Power::dBm(_)) && version != Version::V2_1 =>
+            {
+                writeln!(
+                    out,
+                    "            ! dBm power needs SmartAudio V2.1, VTX speaks {version}"
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_reply<W: Write>(out: &mut W, command: &Command, response: &Response) -> fmt::Result {
+    match (command, response) {
+        (Command::SetPower(command), Response::SetPower(response))
+            if response.power != power_value(command.power) =>
+        {
+            writeln!(
+                out,
+                "            ! VTX reports power {}, requested {}",
+                response.power,
+                power_value(command.power)
+            )
+        }
+        (Command::SetChannel(command), Response::SetChannel(response))
+            if response.channel != command.channel =>
+        {
+            writeln!(
+                out,
+                "            ! VTX reports channel {}, requested {}",
+                Channel(response.channel),
+                Channel(command.channel)
+            )
+        }
+        (Command::SetFrequency(command), Response::SetFrequency(response))
+            if response.frequency != command.frequency =>
+        {
+            writeln!(
+                out,
+                "            ! VTX reports {} MHz, requested {} MHz",
+                response.frequency, command.frequency
+            )
+        }
+        (Command::SetMode(command), Response::SetMode(response))
+            if (
+                response.pitmode_in_range_active,
+                response.pitmode_out_range_active,
+                response.pitmode_enabled,
+                response.unlocked,
+            ) != (
+                command.pitmode_in_range_active,
+                command.pitmode_out_range_active,
+                command.pitmode_enabled,
+                command.unlocked,
+            ) =>
+        {
+            writeln!(out, "            ! VTX reports a different mode")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Power byte a VTX echoes, the level or the dBm value without the MSB.
+fn power_value(power: Power) -> u8 {
+    match power {
+        Power::Level(level) => level,
+        Power::dBm(dbm) => dbm,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use crate::commands::GetSettingsCommand;
+    use crate::commands::SetChannelCommand;
+    use crate::commands::SetPowerCommand;
+    use crate::responses::SetChannelResponse;
+    use crate::responses::Settings;
+    use std::string::String;
+
+    fn settings(version: Version) -> Response {
+        Response::GetSettings(Settings {
+            version,
+            channel: 32,
+            frequency: 5658,
+            unlocked: true,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_channel_names() {
+        assert_eq!(channel_name(0), Some(('A', 1)));
+        assert_eq!(channel_name(15), Some(('B', 8)));
+        assert_eq!(channel_name(32), Some(('R', 1)));
+        assert_eq!(channel_name(39), Some(('R', 8)));
+        assert_eq!(channel_name(40), None);
+    }
+
+    #[test]
+    fn test_transcript() {
+        let exchanges = [
+            Exchange::Transaction {
+                command: Command::GetSettings(GetSettingsCommand {}),
+                response: settings(Version::V2_0),
+                sent_at: 1000,
+                replied_at: 1060,
+            },
+            Exchange::Transaction {
+                command: Command::SetChannel(SetChannelCommand { channel: 32 }),
+                response: Response::SetChannel(SetChannelResponse { channel: 32 }),
+                sent_at: 1200,
+                replied_at: 1287,
+            },
+            Exchange::Transaction {
+                command: Command::SetChannel(SetChannelCommand { channel: 33 }),
+                response: Response::SetChannel(SetChannelResponse { channel: 32 }),
+                sent_at: 1400,
+                replied_at: 1480,
+            },
+            Exchange::Unanswered {
+                command: Command::SetPower(SetPowerCommand {
+                    power: Power::dBm(14),
+                }),
+                sent_at: 1600,
+            },
+            Exchange::Error {
+                error: SmartAudioError::InvalidCrc {
+                    calculated_crc: 0x0A,
+                    frame_crc: 0x0B,
+                },
+                at: 1900,
+            },
+            Exchange::Unsolicited {
+                response: settings(Version::V2_1),
+                at: 2000,
+            },
+        ];
+        let mut out = String::new();
+        let mut transcript = Transcript::new();
+        transcript.write_all(&mut out, exchanges).unwrap();
+        assert_eq!(transcript.version(), Some(Version::V2_1));
+
+        let expected = "    1000 ms → GET_SETTINGS … ← settings V2.0 channel R1 (idx 32) 5658 MHz power 0 after 60 ms
+            VTX speaks SmartAudio V2.0
+    1200 ms → SET_CHANNEL R1 (idx 32) … ← ack channel 32 after 87 ms
+    1400 ms → SET_CHANNEL R2 (idx 33) … ← ack channel 32 after 80 ms
+            ! VTX reports channel R1 (idx 32), requested R2 (idx 33)
+    1600 ms → SET_POWER 14 dBm … no reply
+            ! dBm power needs SmartAudio V2.1, VTX speaks V2.0
+    1900 ms ✗ CRC error: frame 0x0B, calculated 0x0A
+    2000 ms ← settings V2.1 channel R1 (idx 32) 5658 MHz power 0 (unsolicited)
+            ! VTX version changed from V2.0 to V2.1
+";
+        assert_eq!(out, expected, "\n{out}");
+    }
+}